@@ -0,0 +1,276 @@
+use crate::api::client::IflyrecClient;
+use crate::api::constants::DEFAULT_BASE_URL;
+use crate::error::IflyrecError;
+use crate::retry::RetryPolicy;
+use crate::session::SessionProvider;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, Proxy};
+use secrecy::Secret;
+use tokio::time::Duration;
+
+/// 讯飞听见API客户端构建器
+///
+/// 通过 [`IflyrecClient::builder`] 获取，用于配置服务地址、超时、代理等选项
+#[derive(Default)]
+pub struct IflyrecClientBuilder {
+    session_id: Option<String>,
+    session_provider: Option<Box<dyn SessionProvider>>,
+    base_url: Option<String>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    timeout: Option<Duration>,
+    proxies: Vec<Proxy>,
+    no_proxy: bool,
+    default_headers: HeaderMap,
+    user_agent: Option<String>,
+    http_client: Option<Client>,
+    retry_policy: Option<RetryPolicy>,
+    session_expired_codes: Vec<String>,
+}
+
+impl IflyrecClientBuilder {
+    /// 创建新的构建器
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置会话ID
+    pub fn session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
+
+    /// 设置会话ID来源
+    ///
+    /// 在 [`Self::build`] 时获取会话ID；同时设置了 [`Self::session_id`] 时以后者为准
+    pub fn session_provider(mut self, provider: impl SessionProvider + 'static) -> Self {
+        self.session_provider = Some(Box::new(provider));
+        self
+    }
+
+    /// 设置服务地址
+    ///
+    /// 所有接口路径都基于该地址拼接，默认为 [`DEFAULT_BASE_URL`]
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// 设置连接超时
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置读取超时
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    /// 设置单个请求的总超时
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// 添加代理
+    pub fn proxy(mut self, proxy: Proxy) -> Self {
+        self.proxies.push(proxy);
+        self
+    }
+
+    /// 禁用代理（包括系统代理）
+    pub fn no_proxy(mut self) -> Self {
+        self.no_proxy = true;
+        self
+    }
+
+    /// 添加默认请求头
+    pub fn default_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.default_headers.insert(name, value);
+        self
+    }
+
+    /// 设置默认请求头，覆盖之前添加的所有默认请求头
+    pub fn default_headers(mut self, headers: HeaderMap) -> Self {
+        self.default_headers = headers;
+        self
+    }
+
+    /// 设置 User-Agent
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// 使用预先构建的HTTP客户端
+    ///
+    /// 设置后，超时、代理、默认请求头和 User-Agent 选项均不会生效
+    pub fn http_client(mut self, client: Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    /// 设置默认重试策略
+    ///
+    /// 未设置时使用 [`RetryPolicy::default`]
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// 设置表示会话失效的响应码
+    ///
+    /// 服务器以 HTTP 200 返回这些响应码时视为会话失效，返回 [`IflyrecError::AuthError`]。
+    /// 接口的错误码没有公开文档，默认只按 HTTP 401/403 识别会话失效
+    pub fn session_expired_codes<I, S>(mut self, codes: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.session_expired_codes = codes.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// 构建客户端实例
+    pub fn build(self) -> Result<IflyrecClient, IflyrecError> {
+        let session_id = match (self.session_id, &self.session_provider) {
+            (Some(session_id), _) => session_id,
+            (None, Some(provider)) => provider.session_id()?,
+            (None, None) => {
+                return Err(IflyrecError::InvalidParameter(
+                    "session_id or session_provider is required".to_string(),
+                ));
+            }
+        };
+
+        let base_url = self
+            .base_url
+            .unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+            .trim_end_matches('/')
+            .to_string();
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            return Err(IflyrecError::InvalidParameter(format!(
+                "Invalid base URL: {base_url}"
+            )));
+        }
+
+        let http_client = match self.http_client {
+            Some(client) => client,
+            None => {
+                let mut builder = Client::builder().default_headers(self.default_headers);
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+                if let Some(timeout) = self.read_timeout {
+                    builder = builder.read_timeout(timeout);
+                }
+                if let Some(timeout) = self.timeout {
+                    builder = builder.timeout(timeout);
+                }
+                if self.no_proxy {
+                    builder = builder.no_proxy();
+                }
+                for proxy in self.proxies {
+                    builder = builder.proxy(proxy);
+                }
+                if let Some(user_agent) = self.user_agent {
+                    builder = builder.user_agent(user_agent);
+                }
+                builder.build()?
+            }
+        };
+
+        Ok(IflyrecClient::from_parts(
+            Secret::new(session_id),
+            base_url,
+            http_client,
+            self.retry_policy.unwrap_or_default(),
+            self.session_expired_codes.into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::LiteralSession;
+
+    use reqwest::Method;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn build_requires_a_session() {
+        let err = IflyrecClient::builder().build().err().unwrap();
+        assert!(matches!(err, IflyrecError::InvalidParameter(_)));
+    }
+
+    #[test]
+    fn build_uses_session_provider() {
+        let client = IflyrecClient::builder()
+            .session_provider(LiteralSession::new("abc"))
+            .build()
+            .unwrap();
+        let request = client
+            .build_request(Method::GET, "/", "application/json")
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["X-Session-Id"], "abc");
+    }
+
+    #[test]
+    fn build_applies_base_url_and_retry_policy() {
+        let client = IflyrecClient::builder()
+            .session_id("abc")
+            .base_url("http://localhost:8080/")
+            .retry_policy(RetryPolicy::none())
+            .build()
+            .unwrap();
+        assert_eq!(client.url("/orders"), "http://localhost:8080/orders");
+        assert_eq!(client.retry_policy().max_attempts, 1);
+
+        let err = IflyrecClient::builder()
+            .session_id("abc")
+            .base_url("localhost:8080")
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(err, IflyrecError::InvalidParameter(_)));
+    }
+
+    #[tokio::test]
+    async fn build_applies_timeouts() {
+        // 接受连接但从不响应的服务器
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let timeout = Duration::from_millis(50);
+        let builders = [
+            IflyrecClient::builder().timeout(timeout),
+            IflyrecClient::builder().read_timeout(timeout),
+        ];
+        for builder in builders {
+            let client = builder
+                .session_id("abc")
+                .base_url(format!("http://{address}"))
+                .no_proxy()
+                .build()
+                .unwrap();
+            let err = client
+                .build_request(Method::GET, "/", "application/json")
+                .send()
+                .await
+                .unwrap_err();
+            assert!(err.is_timeout(), "{err:?}");
+        }
+        server.abort();
+    }
+}
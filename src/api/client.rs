@@ -1,40 +1,115 @@
+use crate::api::builder::IflyrecClientBuilder;
 use crate::api::constants::*;
 use crate::api::model::*;
-use crate::error::{map_api_error, IflyrecError};
-use crate::util::{calculate_wav_duration, extract_task_name, generate_random_file_name};
+use crate::api::progress::{ProgressEvent, ProgressSink, report};
+use crate::error::{IflyrecError, map_api_error};
+use crate::retry::RetryPolicy;
+use crate::session::SessionProvider;
 
-use reqwest::{Client, Method, RequestBuilder};
+use futures_util::{Stream, TryStreamExt, stream};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
 use secrecy::{ExposeSecret, Secret};
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde_json::json;
-use std::fs;
-use std::path::Path;
-use tokio::time::Duration;
-use tokio_retry::Retry;
-use tokio_retry::strategy::ExponentialBackoff;
+use std::sync::Arc;
 
 /// 讯飞听见API客户端
+///
+/// 克隆开销很小，克隆出的实例共享连接池
+#[derive(Clone)]
 pub struct IflyrecClient {
     /// 会话ID（敏感信息，使用Secret包装）
     session_id: Secret<String>,
+    /// 服务地址
+    base_url: String,
     /// HTTP客户端
     http_client: Client,
+    /// 重试策略
+    retry_policy: RetryPolicy,
+    /// 表示会话失效的响应码
+    session_expired_codes: Arc<[String]>,
 }
 
 impl IflyrecClient {
     /// 创建新的客户端实例
     pub fn new(session_id: String) -> Self {
+        Self::from_parts(
+            Secret::new(session_id),
+            DEFAULT_BASE_URL.to_string(),
+            Client::new(),
+            RetryPolicy::default(),
+            Arc::from([]),
+        )
+    }
+
+    /// 使用会话ID来源创建客户端实例
+    pub fn from_session_provider(provider: &dyn SessionProvider) -> Result<Self, IflyrecError> {
+        Ok(Self::new(provider.session_id()?))
+    }
+
+    /// 创建客户端构建器
+    pub fn builder() -> IflyrecClientBuilder {
+        IflyrecClientBuilder::new()
+    }
+
+    pub(crate) fn from_parts(
+        session_id: Secret<String>,
+        base_url: String,
+        http_client: Client,
+        retry_policy: RetryPolicy,
+        session_expired_codes: Arc<[String]>,
+    ) -> Self {
+        Self {
+            session_id,
+            base_url,
+            http_client,
+            retry_policy,
+            session_expired_codes,
+        }
+    }
+
+    /// 当前的重试策略
+    pub fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    /// 返回使用指定重试策略的客户端副本，用于为单次调用覆盖默认策略
+    ///
+    /// ```no_run
+    /// # async fn example(client: iflypen_api_rs::IflyrecClient) -> Result<(), iflypen_api_rs::IflyrecError> {
+    /// use iflypen_api_rs::RetryPolicy;
+    ///
+    /// let order = client.get_order("order-id").await?;
+    /// let result = client
+    ///     .with_retry_policy(RetryPolicy::none())
+    ///     .get_order_result(&order)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_retry_policy(&self, policy: RetryPolicy) -> Self {
         Self {
-            session_id: Secret::new(session_id),
-            http_client: Client::new(),
+            retry_policy: policy,
+            ..self.clone()
         }
     }
 
+    /// 将接口路径拼接为完整URL
+    pub(crate) fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
     /// 构建请求构建器
     ///
     /// 添加通用的头部信息，如Content-Type、X-Biz-Id和X-Session-Id
-    fn build_request(&self, method: Method, url: &str, content_type: &str) -> RequestBuilder {
+    pub(crate) fn build_request(
+        &self,
+        method: Method,
+        path: &str,
+        content_type: &str,
+    ) -> RequestBuilder {
         self.http_client
-            .request(method, url)
+            .request(method, self.url(path))
             .header("Accept", "application/json, text/plain, */*")
             .header("Content-Type", content_type)
             .header("X-Biz-Id", BIZ_ID)
@@ -42,8 +117,8 @@ impl IflyrecClient {
     }
 
     /// 提交转录订单
-    /// 
-    /// 使用文件ID和转录选项创建新的转录任务
+    ///
+    /// 使用文件ID和转录选项创建新的转录任务，失败时按重试策略重试
     pub async fn submit_transcription_order(
         &self,
         file_id: &str,
@@ -69,24 +144,26 @@ impl IflyrecClient {
             "subtitleCount": ""
         });
 
-        let response = self
-            .build_request(
-                Method::POST,
-                SUBMIT_TRANSCRIPTION_ORDER_URL,
-                "application/json",
-            )
-            .json(&payload)
-            .send()
+        // 重复提交会产生多个计费订单，只重试确定未被受理的请求
+        let biz = self
+            .retry_policy
+            .for_non_idempotent()
+            .run(|| async {
+                let response = self
+                    .build_request(
+                        Method::POST,
+                        SUBMIT_TRANSCRIPTION_ORDER_PATH,
+                        "application/json",
+                    )
+                    .json(&payload)
+                    .send()
+                    .await?;
+                self.parse_response::<BizData>(response).await
+            })
             .await?
-            .json::<SubmitTranscriptionOrderResponse>()
-            .await?;
+            .biz;
 
-        if response.code != "000000" {
-            return Err(map_api_error(&response.code, &response.desc));
-        }
-
-        let order_id = response
-            .biz
+        let order_id = biz
             .and_then(|biz| {
                 if biz.success {
                     Some(biz.order_id)
@@ -100,171 +177,188 @@ impl IflyrecClient {
     }
 
     /// 计算讯飞服务器上的音频时长
-    /// 
+    ///
     /// 在提交转录任务前需要调用此方法
-    pub async fn calculate_duration_on_iflyrec(
-        &self,
-        file_id: &str,
-    ) -> Result<(), IflyrecError> {
-        let url = CALCULATE_DURATION_URL_TEMPLATE.replacen("{}", file_id, 1);
-
-        let response = self
-            .build_request(Method::POST, &url, "application/json")
-            .header("Content-Length", "0")
-            .send()
+    pub async fn calculate_duration_on_iflyrec(&self, file_id: &str) -> Result<(), IflyrecError> {
+        let path = CALCULATE_DURATION_PATH_TEMPLATE.replacen("{}", file_id, 1);
+
+        self.retry_policy
+            .run(|| async {
+                let response = self
+                    .build_request(Method::POST, &path, "application/json")
+                    .header("Content-Length", "0")
+                    .send()
+                    .await?;
+                self.parse_response::<IgnoredAny>(response).await
+            })
             .await?;
+        Ok(())
+    }
 
-        if response.status().is_success() {
-            Ok(())
-        } else {
-            Err(IflyrecError::DurationCalculationError(format!(
-                "Failed to calculate duration for file_id: {file_id}"
-            )))
-        }
+    /// 校验会话ID是否有效
+    ///
+    /// 服务器没有公开的账号信息接口，因此通过请求第一页订单列表校验会话，
+    /// 会话失效时返回 [`IflyrecError::AuthError`]
+    pub async fn validate_session(&self) -> Result<SessionInfo, IflyrecError> {
+        let page = self.get_orders_page(None).await?;
+        Ok(SessionInfo {
+            latest_order: page.orders.into_iter().next(),
+        })
     }
 
     /// 获取最近的转录订单列表
+    ///
+    /// 仅返回第一页，完整的订单历史请使用 [`Self::list_orders`]
     pub async fn get_recent_orders(&self) -> Result<Vec<TranscriptionOrder>, IflyrecError> {
-        let response = self
-            .build_request(Method::POST, GET_RECENT_ORDERS_URL, "application/json")
-            .body("{}")
-            .send()
-            .await?;
+        Ok(self.get_orders_page(None).await?.orders)
+    }
 
-        let response_text = response.text().await?;
-        let response_data = serde_json::from_str::<GetRecentOrdersResponse>(&response_text)?;
+    /// 获取一页订单
+    ///
+    /// `scroll` 为上一页返回的 [`OrderPage::next`]，为 `None` 时获取第一页
+    pub async fn get_orders_page(
+        &self,
+        scroll: Option<ScrollQueryParam>,
+    ) -> Result<OrderPage, IflyrecError> {
+        let request = GetRecentOrdersRequest {
+            scroll_down_query_param: scroll.clone(),
+        };
+        let biz = self
+            .retry_policy
+            .run(|| async {
+                let response = self
+                    .build_request(Method::POST, GET_RECENT_ORDERS_PATH, "application/json")
+                    .json(&request)
+                    .send()
+                    .await?;
+                self.parse_response::<GetRecentOrdersBiz>(response).await
+            })
+            .await?
+            .biz
+            .ok_or_else(|| missing_biz(GET_RECENT_ORDERS_PATH))?;
+
+        let next = next_scroll(
+            scroll.as_ref(),
+            biz.scroll_down_query_param,
+            biz.hj_list.is_empty(),
+        );
+
+        Ok(OrderPage {
+            orders: biz.hj_list,
+            next,
+        })
+    }
 
-        Ok(response_data.biz.hj_list)
+    /// 按时间倒序遍历账号的全部订单
+    ///
+    /// 按需逐页请求，请求失败时返回错误并结束
+    pub fn list_orders(
+        &self,
+    ) -> impl Stream<Item = Result<TranscriptionOrder, IflyrecError>> + Send + '_ {
+        stream::try_unfold(Some(None), move |scroll| async move {
+            let Some(scroll) = scroll else {
+                return Ok(None);
+            };
+            let page = self.get_orders_page(scroll).await?;
+            let orders = stream::iter(page.orders.into_iter().map(Ok));
+            Ok::<_, IflyrecError>(Some((orders, page.next.map(Some))))
+        })
+        .try_flatten()
     }
 
     /// 根据订单ID获取特定订单
-    pub async fn get_order(
+    ///
+    /// 服务器没有查询单个订单的接口，因此从最新一页开始逐页查找订单历史，
+    /// 找到后不再请求后续页；订单不存在时返回 [`IflyrecError::OrderNotFound`]
+    pub async fn get_order(&self, order_id: &str) -> Result<TranscriptionOrder, IflyrecError> {
+        self.find_order(order_id, usize::MAX)
+            .await?
+            .ok_or_else(|| IflyrecError::OrderNotFound(order_id.to_string()))
+    }
+
+    /// 在最新的 `max_pages` 页订单中查找订单
+    ///
+    /// 找到后不再请求后续页；未找到时返回 `None`
+    pub(crate) async fn find_order(
         &self,
         order_id: &str,
+        max_pages: usize,
     ) -> Result<Option<TranscriptionOrder>, IflyrecError> {
-        let orders = self.get_recent_orders().await?;
-        let order = orders.into_iter().find(|item| item.order_id == order_id);
-        Ok(order)
+        let mut scroll = None;
+        for _ in 0..max_pages {
+            let page = self.get_orders_page(scroll).await?;
+            if let Some(order) = page.orders.into_iter().find(|o| o.order_id == order_id) {
+                return Ok(Some(order));
+            }
+            match page.next {
+                Some(next) => scroll = Some(next),
+                None => break,
+            }
+        }
+        Ok(None)
     }
 
-    /// 上传音频文件
-    /// 
-    /// 将本地音频文件上传到讯飞服务器
-    pub async fn upload_audio_file(
+    /// 初始化转录任务
+    ///
+    /// 上传音频文件并提交转录任务
+    pub async fn initiate_transcription_task(
         &self,
         audio_path_str: &str,
         task_name: Option<String>,
+        options: Option<TranscriptionOptions>,
     ) -> Result<String, IflyrecError> {
-        let audio_path = Path::new(audio_path_str);
-
-        let task_name = extract_task_name(audio_path, task_name);
-
-        // 获取文件元数据和时长
-        let metadata = fs::metadata(audio_path)?;
-        let file_size = metadata.len();
-        let audio_time = calculate_wav_duration(file_size);
-
-        // 创建初始元数据以获取 file_id
-        let initial_metadata = AudioMetadata::new(
-            task_name.to_string(),
-            format!("{}{}", DEFAULT_AUDIO_PATH_PREFIX, generate_random_file_name()),
-            file_size,
-            audio_time,
-            0,
-            0,
-            String::new(),
-        );
-
-        let header_block = initial_metadata.to_binary_block()?;
-
-        // 发送初始请求获取 file_id
-        let response = self
-            .build_request(Method::POST, FILE_UPLOAD_URL, "application/octet-stream")
-            .body(header_block)
-            .send()
-            .await?;
-
-        let response_text = response.text().await?;
-        let api_response: FileUploadApiResponse = serde_json::from_str(&response_text)?;
-        let file_id = api_response.biz.file_id;
-
-        // 创建最终元数据
-        let final_metadata = AudioMetadata {
-            is_last: 1,
-            block_index: 1,
-            file_id: file_id.clone(),
-            ..initial_metadata
-        };
+        self.initiate_transcription_task_with_options(
+            audio_path_str,
+            task_name,
+            options,
+            UploadOptions::default(),
+        )
+        .await
+    }
 
-        let final_header_block = final_metadata.to_binary_block()?;
-        let audio_bytes = fs::read(audio_path)?;
-        let mut final_data = final_header_block;
-        final_data.extend_from_slice(&audio_bytes);
+    /// 使用指定上传选项初始化转录任务
+    ///
+    /// `upload_options` 中的进度报告器会同时接收上传和提交阶段的进度事件
+    pub async fn initiate_transcription_task_with_options(
+        &self,
+        audio_path_str: &str,
+        task_name: Option<String>,
+        options: Option<TranscriptionOptions>,
+        upload_options: UploadOptions,
+    ) -> Result<String, IflyrecError> {
+        let progress = upload_options.progress.clone();
 
-        // 发送上传请求
-        let response = self
-            .build_request(Method::POST, FILE_UPLOAD_URL, "application/octet-stream")
-            .body(final_data)
-            .send()
+        // 调用上传函数获取 file_id
+        let file_id = self
+            .upload_audio_file_with_options(audio_path_str, task_name, upload_options)
             .await?;
 
-        if response.status().is_success() {
-            let response_text = response.text().await?;
-            let api_response: FileUploadApiResponse = serde_json::from_str(&response_text)?;
-            if api_response.code == "000000" && api_response.desc == "success" {
-                Ok(file_id)
-            } else {
-                Err(IflyrecError::UploadError(format!(
-                    "{} - {}",
-                    api_response.code, api_response.desc
-                )))
-            }
-        } else {
-            Err(IflyrecError::UploadError(format!(
-                "Upload request failed with status: {}", 
-                response.status()
-            )))
-        }
+        self.submit_uploaded_audio(&file_id, options, progress.as_ref())
+            .await
     }
 
-    /// 初始化转录任务
-    /// 
-    /// 上传音频文件并提交转录任务
-    pub async fn initiate_transcription_task(
+    /// 为已上传的音频提交转录任务
+    ///
+    /// 计算音频时长后提交转录订单，适用于单独上传或续传完成的文件
+    pub async fn submit_uploaded_audio(
         &self,
-        audio_path_str: &str,
-        task_name: Option<String>,
+        file_id: &str,
         options: Option<TranscriptionOptions>,
+        progress: Option<&ProgressSink>,
     ) -> Result<String, IflyrecError> {
-        // 调用上传函数获取 file_id
-        let file_id = self.upload_audio_file(audio_path_str, task_name).await?;
-
-        // 后续处理步骤
-        self.calculate_duration_on_iflyrec(&file_id).await?;
-
-        let retry_strategy = ExponentialBackoff::from_millis(500)
-            .max_delay(Duration::from_secs(10))
-            .take(5); // 最多重试5次
-
-        let order_id = Retry::spawn(retry_strategy, || async {
-            match self
-                .submit_transcription_order(&file_id, options.clone())
-                .await
-            {
-                Ok(order_id) => Ok(order_id),
-                Err(e) => {
-                    // 检查是否是可重试的错误（订单音频时长计算中）
-                    if let IflyrecError::ApiError { desc, .. } = &e {
-                        if desc.contains("订单音频时长计算中") {
-                            // 标记为可重试的错误，但不改变错误本身
-                            tracing::info!("订单音频时长计算中，可以重试");
-                        }
-                    }
-                    Err(e)
-                }
-            }
-        })
-        .await?;
+        report(progress, ProgressEvent::CalculatingDuration);
+        self.calculate_duration_on_iflyrec(file_id).await?;
+
+        report(progress, ProgressEvent::SubmittingOrder);
+
+        let order_id = self.submit_transcription_order(file_id, options).await?;
+
+        report(
+            progress,
+            ProgressEvent::OrderSubmitted {
+                order_id: order_id.clone(),
+            },
+        );
 
         Ok(order_id)
     }
@@ -274,20 +368,213 @@ impl IflyrecClient {
         &self,
         order: &TranscriptionOrder,
     ) -> Result<TranscriptResult, IflyrecError> {
-        let url = GET_ORDER_RESULT_URL_TEMPLATE
+        let path = GET_ORDER_RESULT_PATH_TEMPLATE
             .replacen("{}", &order.order_id, 1)
             .replacen("{}", &order.origin_audio_id, 1);
 
-        let response = self
-            .build_request(Method::GET, &url, "application/json")
-            .send()
-            .await?;
-
-        let response_text = response.text().await?;
-        let response_data = serde_json::from_str::<GetOrderResultResponse>(&response_text)?;
-        let text = response_data.biz.transcript_result;
+        let text = self
+            .retry_policy
+            .run(|| async {
+                let response = self
+                    .build_request(Method::GET, &path, "application/json")
+                    .send()
+                    .await?;
+                self.parse_response::<GetOrderResultBiz>(response).await
+            })
+            .await?
+            .biz
+            .ok_or_else(|| missing_biz(&path))?
+            .transcript_result;
         let transcript_result = serde_json::from_str::<TranscriptResult>(&text)?;
 
         Ok(transcript_result)
     }
 }
+
+/// 由服务器返回的游标得到下一页的查询参数
+///
+/// 空页或游标未前进时视为最后一页，避免服务器重复返回同一游标时无限翻页
+fn next_scroll(
+    current: Option<&ScrollQueryParam>,
+    returned: Option<ScrollQueryParam>,
+    page_is_empty: bool,
+) -> Option<ScrollQueryParam> {
+    returned.filter(|next| !page_is_empty && current != Some(next))
+}
+
+impl IflyrecClient {
+    /// 解析接口通用响应
+    ///
+    /// 先校验 `code`，非成功时通过 [`map_api_error`] 返回错误，再解析业务数据。
+    /// HTTP 401/403 和配置的会话失效响应码均返回 [`IflyrecError::AuthError`]
+    pub(crate) async fn parse_response<T: DeserializeOwned>(
+        &self,
+        response: Response,
+    ) -> Result<ApiResponse<T>, IflyrecError> {
+        parse_response(response, &self.session_expired_codes).await
+    }
+}
+
+async fn parse_response<T: DeserializeOwned>(
+    response: Response,
+    session_expired_codes: &[String],
+) -> Result<ApiResponse<T>, IflyrecError> {
+    let status = response.status();
+    let response_text = response.text().await?;
+
+    let envelope = match serde_json::from_str::<ApiResponse<serde_json::Value>>(&response_text) {
+        Ok(envelope) => envelope,
+        // 非 JSON 的错误页面按 HTTP 状态码报告
+        Err(_) if is_auth_status(status) => {
+            return Err(IflyrecError::AuthError(format!(
+                "session rejected: HTTP {status}"
+            )));
+        }
+        Err(_) if !status.is_success() => {
+            return Err(IflyrecError::HttpStatus {
+                status: status.as_u16(),
+                reason: status.canonical_reason().unwrap_or_default().to_string(),
+            });
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if envelope.code != SUCCESS_CODE {
+        if session_expired_codes.contains(&envelope.code) {
+            return Err(session_rejected(&envelope.code, &envelope.desc));
+        }
+        return Err(match map_api_error(&envelope.code, &envelope.desc) {
+            // 未收录的错误码随 401/403 返回时同样视为会话失效
+            IflyrecError::ApiError { code, desc } if is_auth_status(status) => {
+                session_rejected(&code, &desc)
+            }
+            e => e,
+        });
+    }
+
+    let biz = envelope
+        .biz
+        .filter(|biz| !biz.is_null())
+        .map(serde_json::from_value)
+        .transpose()?;
+
+    Ok(ApiResponse {
+        code: envelope.code,
+        desc: envelope.desc,
+        biz,
+    })
+}
+
+/// 服务器拒绝会话
+fn session_rejected(code: &str, desc: &str) -> IflyrecError {
+    IflyrecError::AuthError(format!("session rejected: {code} - {desc}"))
+}
+
+/// HTTP 状态码是否表示认证失败
+fn is_auth_status(status: StatusCode) -> bool {
+    matches!(status, StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN)
+}
+
+/// 成功响应中缺少业务数据
+fn missing_biz(path: &str) -> IflyrecError {
+    IflyrecError::Unknown(format!("Missing biz in response from {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(hj_id: &str, create_time: i64) -> ScrollQueryParam {
+        ScrollQueryParam {
+            hj_id: Some(hj_id.to_string()),
+            sort_hj_create_time: Some(create_time),
+            ..ScrollQueryParam::default()
+        }
+    }
+
+    #[test]
+    fn advancing_cursor_continues() {
+        let next = cursor("b", 200);
+        assert_eq!(
+            next_scroll(None, Some(next.clone()), false),
+            Some(next.clone())
+        );
+        assert_eq!(
+            next_scroll(Some(&cursor("a", 300)), Some(next.clone()), false),
+            Some(next)
+        );
+    }
+
+    #[test]
+    fn missing_cursor_ends() {
+        assert_eq!(next_scroll(None, None, false), None);
+        assert_eq!(next_scroll(Some(&cursor("a", 300)), None, false), None);
+    }
+
+    #[test]
+    fn empty_page_ends() {
+        assert_eq!(next_scroll(None, Some(cursor("b", 200)), true), None);
+    }
+
+    #[test]
+    fn repeated_cursor_ends() {
+        let current = cursor("a", 300);
+        assert_eq!(
+            next_scroll(Some(&current), Some(current.clone()), false),
+            None
+        );
+    }
+
+    fn response(status: u16, body: &str) -> Response {
+        http::Response::builder()
+            .status(status)
+            .body(body.to_string())
+            .unwrap()
+            .into()
+    }
+
+    async fn parse(status: u16, body: &str) -> Result<ApiResponse<BizData>, IflyrecError> {
+        parse_response(response(status, body), &["100001".to_string()]).await
+    }
+
+    #[tokio::test]
+    async fn non_json_auth_status_is_auth_error() {
+        let err = parse(401, "<html>Unauthorized</html>").await.unwrap_err();
+        assert!(err.is_auth_error(), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn non_json_error_status_keeps_http_status() {
+        let err = parse(502, "Bad Gateway").await.unwrap_err();
+        assert!(matches!(err, IflyrecError::HttpStatus { status: 502, .. }));
+    }
+
+    #[tokio::test]
+    async fn json_auth_status_with_error_code_is_auth_error() {
+        let err = parse(401, r#"{"code":"300001","desc":"invalid session"}"#)
+            .await
+            .unwrap_err();
+        assert!(err.is_auth_error(), "{err:?}");
+    }
+
+    #[tokio::test]
+    async fn configured_session_code_is_auth_error() {
+        let err = parse(200, r#"{"code":"100001","desc":"session expired"}"#)
+            .await
+            .unwrap_err();
+        assert!(err.is_auth_error(), "{err:?}");
+
+        let err = parse(200, r#"{"code":"300001","desc":"other"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IflyrecError::ApiError { code, .. } if code == "300001"));
+    }
+
+    #[tokio::test]
+    async fn null_biz_is_none() {
+        let parsed = parse(200, r#"{"code":"000000","desc":"ok","biz":null}"#)
+            .await
+            .unwrap();
+        assert!(parsed.biz.is_none());
+    }
+}
@@ -0,0 +1,225 @@
+//! 长音频转录
+//!
+//! 将超长录音切分为多个分段，分别提交转录订单，等待全部完成后合并转录结果。
+//! 已提交订单后出错时，错误中附带已提交的分段订单，可通过 [`IflyrecClient::collect_long_audio`]
+//! 继续等待，无需重新提交（重复提交会重复计费）
+
+use crate::api::client::IflyrecClient;
+use crate::api::model::*;
+use crate::api::upload::preprocess_audio;
+use crate::api::wait::WaitOptions;
+use crate::audio::{AudioSegment, SplitOptions, split_wav};
+use crate::error::IflyrecError;
+use crate::util::{extract_task_name, temporary_path};
+
+use std::path::Path;
+use tokio::fs;
+use tokio::time::Instant;
+
+/// 长音频转录选项
+#[derive(Clone, Debug, Default)]
+pub struct LongAudioOptions {
+    /// 分段选项
+    pub split: SplitOptions,
+    /// 等待选项，其中 `timeout` 为等待所有订单完成的最长时间
+    pub wait: WaitOptions,
+}
+
+/// 分段订单
+#[derive(Clone, Debug)]
+pub struct SegmentOrder {
+    /// 订单ID
+    pub order_id: String,
+    /// 分段在原音频中的起始时间（毫秒）
+    pub offset_ms: u64,
+    /// 分段时长（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 长音频转录结果
+#[derive(Clone, Debug)]
+pub struct LongAudioTranscript {
+    /// 各分段的订单，按时间顺序排列
+    pub segments: Vec<SegmentOrder>,
+    /// 合并后的转录结果，时间已对齐到原音频
+    pub result: TranscriptResult,
+}
+
+impl IflyrecClient {
+    /// 转录长音频
+    ///
+    /// 音频（需为 PCM WAV，或通过 `upload_options.preprocess` 转码）按 `long_options.split`
+    /// 切分后，每个分段作为独立订单提交；所有订单完成后合并转录结果。
+    /// 已提交订单后出错时返回 [`IflyrecError::LongAudioFailed`]
+    pub async fn transcribe_long_audio(
+        &self,
+        audio_path_str: &str,
+        task_name: Option<String>,
+        options: Option<TranscriptionOptions>,
+        upload_options: UploadOptions,
+        long_options: LongAudioOptions,
+    ) -> Result<LongAudioTranscript, IflyrecError> {
+        let deadline = Instant::now() + long_options.wait.timeout;
+        let segments = self
+            .submit_long_audio(
+                audio_path_str,
+                task_name,
+                options,
+                upload_options,
+                &long_options.split,
+            )
+            .await?;
+        self.collect_until(&segments, &long_options.wait, deadline)
+            .await
+            .map_err(|e| long_audio_failed(segments, e))
+    }
+
+    /// 切分长音频并逐段提交转录订单，返回按时间顺序排列的分段订单
+    ///
+    /// 部分分段已提交后出错时返回 [`IflyrecError::LongAudioFailed`]，其中附带已提交的分段订单
+    pub async fn submit_long_audio(
+        &self,
+        audio_path_str: &str,
+        task_name: Option<String>,
+        options: Option<TranscriptionOptions>,
+        upload_options: UploadOptions,
+        split_options: &SplitOptions,
+    ) -> Result<Vec<SegmentOrder>, IflyrecError> {
+        let source_path = Path::new(audio_path_str);
+        let task_name = extract_task_name(source_path, task_name);
+
+        let work_dir = temporary_path("");
+        fs::create_dir_all(&work_dir).await?;
+
+        let result = self
+            .submit_segments(
+                source_path,
+                &work_dir,
+                &task_name,
+                options,
+                upload_options,
+                split_options,
+            )
+            .await;
+
+        if let Err(e) = fs::remove_dir_all(&work_dir).await {
+            tracing::warn!("清理临时目录 {} 失败：{e}", work_dir.display());
+        }
+
+        result
+    }
+
+    /// 等待分段订单全部完成并合并转录结果
+    ///
+    /// 出错时可使用同一组分段订单再次调用
+    pub async fn collect_long_audio(
+        &self,
+        segments: &[SegmentOrder],
+        options: &WaitOptions,
+    ) -> Result<LongAudioTranscript, IflyrecError> {
+        let deadline = Instant::now() + options.timeout;
+        self.collect_until(segments, options, deadline).await
+    }
+
+    async fn collect_until(
+        &self,
+        segments: &[SegmentOrder],
+        options: &WaitOptions,
+        deadline: Instant,
+    ) -> Result<LongAudioTranscript, IflyrecError> {
+        let mut parts = Vec::with_capacity(segments.len());
+        for segment in segments {
+            let order = self
+                .wait_until(&segment.order_id, options, deadline)
+                .await?;
+            let result = self.get_order_result(&order).await?;
+            parts.push((result, segment.offset_ms as i64));
+        }
+
+        Ok(LongAudioTranscript {
+            segments: segments.to_vec(),
+            result: TranscriptResult::merge(parts),
+        })
+    }
+
+    async fn submit_segments(
+        &self,
+        source_path: &Path,
+        work_dir: &Path,
+        task_name: &str,
+        options: Option<TranscriptionOptions>,
+        upload_options: UploadOptions,
+        split_options: &SplitOptions,
+    ) -> Result<Vec<SegmentOrder>, IflyrecError> {
+        // 先对完整音频转码，再切分
+        let preprocessed = preprocess_audio(source_path, upload_options.preprocess).await?;
+        let split_source = preprocessed.as_deref().unwrap_or(source_path);
+        let audio_segments = split_wav(split_source, work_dir, split_options).await;
+        if let Some(path) = &preprocessed {
+            fs::remove_file(path).await?;
+        }
+        let audio_segments = audio_segments?;
+
+        let segment_count = audio_segments.len();
+        tracing::info!("音频切分为 {segment_count} 个分段");
+
+        // 逐段上传并提交订单
+        let segment_upload_options = UploadOptions {
+            preprocess: AudioPreprocess::None,
+            manifest_path: None,
+            ..upload_options
+        };
+        let mut segments = Vec::with_capacity(segment_count);
+        for (
+            i,
+            AudioSegment {
+                path,
+                offset_ms,
+                duration_ms,
+            },
+        ) in audio_segments.into_iter().enumerate()
+        {
+            let segment_name = if segment_count > 1 {
+                format!("{task_name} ({}/{segment_count})", i + 1)
+            } else {
+                task_name.to_string()
+            };
+            let order_id = match self
+                .initiate_transcription_task_with_options(
+                    &path.to_string_lossy(),
+                    Some(segment_name),
+                    options.clone(),
+                    segment_upload_options.clone(),
+                )
+                .await
+            {
+                Ok(order_id) => order_id,
+                Err(e) => return Err(long_audio_failed(segments, e)),
+            };
+            if let Err(e) = fs::remove_file(&path).await {
+                tracing::warn!("删除分段文件 {} 失败：{e}", path.display());
+            }
+
+            tracing::info!("分段 {}/{segment_count} 已提交，订单 ID: {order_id}", i + 1);
+            segments.push(SegmentOrder {
+                order_id,
+                offset_ms,
+                duration_ms,
+            });
+        }
+
+        Ok(segments)
+    }
+}
+
+/// 已提交分段订单后出错时附带分段订单，尚未提交任何订单时返回原错误
+fn long_audio_failed(segments: Vec<SegmentOrder>, error: IflyrecError) -> IflyrecError {
+    if segments.is_empty() {
+        error
+    } else {
+        IflyrecError::LongAudioFailed {
+            segments,
+            source: Box::new(error),
+        }
+    }
+}
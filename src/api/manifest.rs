@@ -0,0 +1,202 @@
+//! 上传清单
+//!
+//! 记录一次上传的文件信息、`file_id` 以及已确认的数据块，用于在上传中断后续传
+
+use crate::api::model::AudioMetadata;
+use crate::error::IflyrecError;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::fs::{self, File};
+use tokio::io::AsyncReadExt;
+
+/// 上传清单
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadManifest {
+    /// 本地音频文件的绝对路径
+    pub file_path: PathBuf,
+    /// 文件大小
+    pub file_size: u64,
+    /// 文件修改时间
+    pub modified: SystemTime,
+    /// 文件内容的 SHA-256 哈希（十六进制）
+    pub content_hash: String,
+    /// 上传使用的分块大小
+    pub block_size: u64,
+    /// 上传时使用的音频元数据，其中包含 `file_id`
+    pub metadata: AudioMetadata,
+    /// 服务器已确认的数据块序号
+    pub acknowledged_blocks: BTreeSet<u64>,
+    /// `file_path` 是否为转码生成的临时文件，是则在上传完成后删除
+    #[serde(default)]
+    pub temporary: bool,
+}
+
+impl UploadManifest {
+    /// 音频文件对应的默认清单路径
+    ///
+    /// 即在音频文件名后追加 `.upload.json`
+    pub fn default_path(audio_path: &Path) -> PathBuf {
+        let mut path = audio_path.as_os_str().to_owned();
+        path.push(".upload.json");
+        PathBuf::from(path)
+    }
+
+    /// 为本地音频文件创建清单，记录文件的绝对路径、大小、修改时间和内容哈希
+    pub(crate) async fn create(
+        audio_path: &Path,
+        block_size: u64,
+        metadata: AudioMetadata,
+        temporary: bool,
+    ) -> Result<Self, IflyrecError> {
+        // 保存绝对路径，从其他工作目录续传时仍能找到文件
+        let file_path = fs::canonicalize(audio_path).await?;
+        let file_metadata = fs::metadata(&file_path).await?;
+        Ok(Self {
+            file_size: file_metadata.len(),
+            modified: file_metadata.modified()?,
+            content_hash: hash_file(&file_path).await?,
+            file_path,
+            block_size,
+            metadata,
+            acknowledged_blocks: BTreeSet::new(),
+            temporary,
+        })
+    }
+
+    /// `file_id`
+    pub fn file_id(&self) -> &str {
+        &self.metadata.file_id
+    }
+
+    /// 从磁盘读取清单
+    pub async fn load(path: &Path) -> Result<Self, IflyrecError> {
+        let content = fs::read(path).await?;
+        Ok(serde_json::from_slice(&content)?)
+    }
+
+    /// 将清单写入磁盘
+    ///
+    /// 先写入临时文件再重命名，避免中断时留下不完整的清单
+    pub async fn save(&self, path: &Path) -> Result<(), IflyrecError> {
+        let content = serde_json::to_vec_pretty(self)?;
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, content).await?;
+        fs::rename(&tmp_path, path).await?;
+        Ok(())
+    }
+
+    /// 校验本地文件自清单创建以来未被修改
+    pub async fn verify_file(&self) -> Result<(), IflyrecError> {
+        let metadata = fs::metadata(&self.file_path).await?;
+        if metadata.len() != self.file_size || metadata.modified()? != self.modified {
+            return Err(IflyrecError::UploadError(format!(
+                "Audio file changed since upload started: {}",
+                self.file_path.display()
+            )));
+        }
+
+        if hash_file(&self.file_path).await? != self.content_hash {
+            return Err(IflyrecError::UploadError(format!(
+                "Audio file content hash mismatch: {}",
+                self.file_path.display()
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// 计算文件内容的 SHA-256 哈希
+pub(crate) async fn hash_file(path: &Path) -> Result<String, IflyrecError> {
+    let mut file = File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::temporary_path;
+
+    fn metadata() -> AudioMetadata {
+        AudioMetadata::new(
+            "task".to_string(),
+            "tjb1/audio.wav".to_string(),
+            4,
+            "1000".to_string(),
+            0,
+            0,
+            "file-id".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn save_and_load_round_trip() {
+        let audio_path = temporary_path(".wav");
+        let manifest_path = UploadManifest::default_path(&audio_path);
+        fs::write(&audio_path, b"RIFF").await.unwrap();
+
+        let mut manifest = UploadManifest::create(&audio_path, 2, metadata(), true)
+            .await
+            .unwrap();
+        manifest.acknowledged_blocks.insert(1);
+        manifest.save(&manifest_path).await.unwrap();
+        let loaded = UploadManifest::load(&manifest_path).await.unwrap();
+        fs::remove_file(&audio_path).await.unwrap();
+        fs::remove_file(&manifest_path).await.unwrap();
+
+        assert!(loaded.file_path.is_absolute());
+        assert_eq!(loaded.file_path, manifest.file_path);
+        assert_eq!(loaded.file_size, 4);
+        assert_eq!(loaded.modified, manifest.modified);
+        assert_eq!(loaded.content_hash, manifest.content_hash);
+        assert_eq!(loaded.block_size, 2);
+        assert_eq!(loaded.file_id(), "file-id");
+        assert_eq!(loaded.acknowledged_blocks, BTreeSet::from([1]));
+        assert!(loaded.temporary);
+    }
+
+    #[tokio::test]
+    async fn verify_file_rejects_changed_file() {
+        let audio_path = temporary_path(".wav");
+        fs::write(&audio_path, b"RIFF").await.unwrap();
+        let manifest = UploadManifest::create(&audio_path, 2, metadata(), false)
+            .await
+            .unwrap();
+        manifest.verify_file().await.unwrap();
+
+        // 大小不变、修改时间相同时由内容哈希发现修改
+        let modified = manifest.modified;
+        fs::write(&audio_path, b"RIFX").await.unwrap();
+        let file = std::fs::File::options()
+            .write(true)
+            .open(&audio_path)
+            .unwrap();
+        file.set_modified(modified).unwrap();
+        drop(file);
+        let same_size = manifest.verify_file().await;
+
+        fs::write(&audio_path, b"RIFF!").await.unwrap();
+        let resized = manifest.verify_file().await;
+        fs::remove_file(&audio_path).await.unwrap();
+
+        assert!(matches!(same_size, Err(IflyrecError::UploadError(_))));
+        assert!(matches!(resized, Err(IflyrecError::UploadError(_))));
+    }
+}
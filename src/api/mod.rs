@@ -1,12 +1,32 @@
 //! API模块
-//! 
+//!
 //! 包含与讯飞听见API交互的所有功能
 
-mod constants;
+mod builder;
 mod client;
+mod constants;
+mod long_audio;
+mod manifest;
 mod model;
+mod pool;
+mod progress;
+mod status;
+mod upload;
+mod wait;
+mod watcher;
 
 // 重新导出公共类型和函数
+pub use builder::IflyrecClientBuilder;
 pub use client::IflyrecClient;
 pub use constants::*;
-pub use model::{AudioMetadata, TranscriptionOptions, TranscriptionOrder, TranscriptResult};
\ No newline at end of file
+pub use long_audio::{LongAudioOptions, LongAudioTranscript, SegmentOrder};
+pub use manifest::UploadManifest;
+pub use model::{
+    AudioMetadata, AudioPreprocess, OrderPage, ScrollQueryParam, SessionInfo, TranscriptResult,
+    TranscriptionOptions, TranscriptionOrder, UploadOptions,
+};
+pub use pool::{Account, AccountPool, PooledOrder};
+pub use progress::{ProgressEvent, ProgressReporter, ProgressSink};
+pub use status::{HjStatus, LockStatus, OrderStatus, TranscriptStatus};
+pub use wait::{OrderState, WaitOptions};
+pub use watcher::{OrderEvent, OrderUpdate, OrderWatcher};
@@ -1,11 +1,21 @@
+use crate::api::constants::DEFAULT_UPLOAD_BLOCK_SIZE;
+use crate::api::progress::ProgressSink;
+use crate::api::status::{HjStatus, LockStatus, OrderStatus, TranscriptStatus};
+
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
-/// 文件上传API响应
+/// 接口通用响应
+///
+/// 所有接口均返回 `code`、`desc` 和业务数据 `biz`，`code` 为 [`SUCCESS_CODE`] 时表示成功
+///
+/// [`SUCCESS_CODE`]: crate::api::SUCCESS_CODE
 #[derive(Debug, Deserialize)]
-pub struct FileUploadApiResponse {
+pub struct ApiResponse<T> {
     pub code: String,
+    #[serde(default)]
     pub desc: String,
-    pub biz: FileUploadApiBizData,
+    pub biz: Option<T>,
 }
 
 /// 文件上传API业务数据
@@ -15,14 +25,6 @@ pub struct FileUploadApiBizData {
     pub file_id: String,
 }
 
-/// 提交转录任务响应
-#[derive(Debug, Deserialize)]
-pub struct SubmitTranscriptionOrderResponse {
-    pub code: String,
-    pub desc: String,
-    pub biz: Option<BizData>,
-}
-
 /// 业务数据
 #[derive(Debug, Deserialize)]
 pub struct BizData {
@@ -32,7 +34,10 @@ pub struct BizData {
 }
 
 /// 转录选项
-#[derive(Clone, Debug)]
+///
+/// 反序列化时缺省的字段使用默认值
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
 pub struct TranscriptionOptions {
     pub need_sms: bool,
     /// 热词，多个词语之间请使用中文逗号“，”分隔。
@@ -50,8 +55,49 @@ impl Default for TranscriptionOptions {
     }
 }
 
+/// 上传选项
+#[derive(Clone, Debug)]
+pub struct UploadOptions {
+    /// 每个数据块包含的音频字节数
+    pub block_size: u64,
+    /// 同时上传的数据块数量上限，为 1 时按顺序上传
+    pub concurrency: usize,
+    /// 上传清单路径，设置后可在上传中断时续传
+    pub manifest_path: Option<PathBuf>,
+    /// 进度报告器
+    pub progress: Option<ProgressSink>,
+    /// 上传前的音频预处理方式
+    pub preprocess: AudioPreprocess,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            block_size: DEFAULT_UPLOAD_BLOCK_SIZE,
+            concurrency: 1,
+            manifest_path: None,
+            progress: None,
+            preprocess: AudioPreprocess::None,
+        }
+    }
+}
+
+/// 上传前的音频预处理方式
+///
+/// 转码目标为录音笔渠道期望的 16 kHz 单声道 16 位 PCM WAV，需要启用 `transcode` 特性
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AudioPreprocess {
+    /// 原样上传
+    #[default]
+    None,
+    /// 仅在音频不是目标格式时转码
+    Auto,
+    /// 总是转码
+    Always,
+}
+
 /// 音频元数据
-#[derive(Serialize, Debug)]
+#[derive(Clone, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct AudioMetadata {
     pub audio_name: String,
@@ -97,14 +143,6 @@ impl AudioMetadata {
     }
 }
 
-/// 获取订单结果响应
-#[derive(Debug, Deserialize)]
-pub struct GetOrderResultResponse {
-    // pub code: String,
-    // pub desc: String,
-    pub biz: GetOrderResultBiz,
-}
-
 /// 获取订单结果业务数据
 #[derive(Debug, Deserialize)]
 pub struct GetOrderResultBiz {
@@ -122,7 +160,7 @@ pub struct GetOrderResultBiz {
 }
 
 /// 转录结果
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TranscriptResult {
     // pub images: Vec<Value>,
     #[serde(rename = "ps")]
@@ -133,8 +171,35 @@ pub struct TranscriptResult {
     // pub styles: Vec<Value>,
 }
 
+impl TranscriptResult {
+    /// 合并多个分段的转录结果
+    ///
+    /// `parts` 中每一项为分段的转录结果及其在原音频中的起始时间（毫秒），
+    /// 段落和单词的时间会加上该偏移；角色按 `role` 去重，保留首次出现的角色。
+    pub fn merge(parts: impl IntoIterator<Item = (TranscriptResult, i64)>) -> Self {
+        let mut merged = Self {
+            paragraphs: Vec::new(),
+            roles: Vec::new(),
+        };
+
+        for (part, offset_ms) in parts {
+            for mut paragraph in part.paragraphs {
+                paragraph.shift_time(offset_ms);
+                merged.paragraphs.push(paragraph);
+            }
+            for role in part.roles {
+                if !merged.roles.iter().any(|r| r.role == role.role) {
+                    merged.roles.push(role);
+                }
+            }
+        }
+
+        merged
+    }
+}
+
 /// 段落
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Paragraph {
     // paragraph_time 应该为一个 2 元素的数组，分别表示开始和结束时间
     #[serde(rename = "pTime")]
@@ -143,8 +208,22 @@ pub struct Paragraph {
     pub words: Vec<Word>,
 }
 
+impl Paragraph {
+    /// 将段落及其中所有单词的时间平移 `offset_ms` 毫秒
+    pub fn shift_time(&mut self, offset_ms: i64) {
+        for time in &mut self.paragraph_time {
+            *time += offset_ms;
+        }
+        for word in &mut self.words {
+            for time in &mut word.time {
+                *time += offset_ms;
+            }
+        }
+    }
+}
+
 /// 单词
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Word {
     pub modal: bool,
     #[serde(rename = "rl")]
@@ -155,7 +234,7 @@ pub struct Word {
 }
 
 /// 角色
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Role {
     pub name: String,
     pub role: String,
@@ -163,14 +242,6 @@ pub struct Role {
     pub updated_at: i64,
 }
 
-/// 获取最近订单响应
-#[derive(Debug, Deserialize)]
-pub struct GetRecentOrdersResponse {
-    pub biz: GetRecentOrdersBiz,
-    // pub code: String,
-    // pub desc: String,
-}
-
 /// 获取最近订单业务数据
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -178,7 +249,18 @@ pub struct GetRecentOrdersBiz {
     // pub count: i32,
     pub hj_list: Vec<TranscriptionOrder>,
     // pub scroll_up_query_param: ScrollQueryParam,
-    // pub scroll_down_query_param: ScrollQueryParam,
+    #[serde(default)]
+    pub scroll_down_query_param: Option<ScrollQueryParam>,
+}
+
+/// 获取最近订单请求
+///
+/// 不带滚动参数时返回第一页
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetRecentOrdersRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scroll_down_query_param: Option<ScrollQueryParam>,
 }
 
 /// 转录订单
@@ -187,7 +269,7 @@ pub struct GetRecentOrdersBiz {
 pub struct TranscriptionOrder {
     pub order_id: String,
     pub origin_audio_id: String,
-    pub order_status: String,
+    pub order_status: OrderStatus,
     pub order_name: String,
     pub create_time: i64,
     pub favorite_time: Option<i64>,
@@ -199,12 +281,12 @@ pub struct TranscriptionOrder {
     pub full_text_abstract: Option<String>,
     pub hj_size: i64,
     pub favorite_status: Option<String>,
-    pub hj_status: Option<String>,
+    pub hj_status: Option<HjStatus>,
     pub file_source: String,
     pub file_re_source: String,
-    pub hj_lock_status: String,
+    pub hj_lock_status: LockStatus,
     pub order_type: String,
-    pub transcript_status: Option<String>,
+    pub transcript_status: Option<TranscriptStatus>,
     pub type_: i32,
     pub output_type: i32,
     pub expedite_transcript: String,
@@ -214,12 +296,180 @@ pub struct TranscriptionOrder {
     pub thumbnail_link_list: Option<Vec<String>>,
 }
 
+impl TranscriptionOrder {
+    /// 订单是否已完成，可以获取转录结果
+    pub fn is_finished(&self) -> bool {
+        self.order_status == OrderStatus::Completed
+    }
+
+    /// 订单是否仍在处理中
+    ///
+    /// 失败订单的状态取值尚未确认，未完成的订单（包括状态未知的订单）均视为处理中
+    pub fn is_in_progress(&self) -> bool {
+        !self.is_finished()
+    }
+}
+
+#[cfg(test)]
+impl TranscriptionOrder {
+    /// 构造测试用的订单
+    pub(crate) fn for_test(order_id: &str, order_status: &str) -> Self {
+        serde_json::from_value(serde_json::json!({
+            "orderId": order_id,
+            "originAudioId": "",
+            "orderStatus": order_status,
+            "orderName": order_id,
+            "createTime": 0,
+            "lastOperateTime": 0,
+            "audioDurations": 0,
+            "hjFrom": "",
+            "hjFromDesc": "",
+            "keyword": [],
+            "hjSize": 0,
+            "fileSource": "",
+            "fileReSource": "",
+            "hjLockStatus": "0",
+            "orderType": "",
+            "type": 0,
+            "outputType": 0,
+            "expediteTranscript": "",
+            "fileId": "",
+            "redPointStatus": 0,
+        }))
+        .unwrap()
+    }
+}
+
+/// 会话信息
+#[derive(Clone, Debug)]
+pub struct SessionInfo {
+    /// 账号最近的订单，账号没有订单时为 `None`
+    pub latest_order: Option<TranscriptionOrder>,
+}
+
 /// 滚动查询参数
-#[derive(Debug, Deserialize)]
+///
+/// 订单列表以游标分页，服务器在每页返回下一页的查询参数
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ScrollQueryParam {
-    // pub hj_id: Option<String>,
-    // pub transcript_id: String,
-    // pub sort_hj_create_time: Option<i64>,
-    // pub sort_trans_create_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hj_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transcript_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_hj_create_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_trans_create_time: Option<i64>,
+}
+
+/// 一页订单
+#[derive(Clone, Debug)]
+pub struct OrderPage {
+    /// 本页订单
+    pub orders: Vec<TranscriptionOrder>,
+    /// 下一页的查询参数，为 `None` 时表示已到最后一页
+    pub next: Option<ScrollQueryParam>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn word(text: &str, start: i64, end: i64) -> serde_json::Value {
+        json!({ "modal": false, "rl": "1", "text": text, "time": [start, end], "wp": "n" })
+    }
+
+    fn role(role: &str, name: &str) -> serde_json::Value {
+        json!({ "name": name, "role": role, "updatedAt": 0 })
+    }
+
+    fn transcript(paragraphs: serde_json::Value, roles: serde_json::Value) -> TranscriptResult {
+        serde_json::from_value(json!({ "ps": paragraphs, "roles": roles })).unwrap()
+    }
+
+    fn times(result: &TranscriptResult) -> Vec<(Vec<i64>, Vec<Vec<i64>>)> {
+        result
+            .paragraphs
+            .iter()
+            .map(|p| {
+                let words = p.words.iter().map(|w| w.time.clone()).collect();
+                (p.paragraph_time.clone(), words)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn shift_time_moves_paragraph_and_words() {
+        let mut result = transcript(
+            json!([{ "pTime": [100, 900], "role": "1", "words": [word("a", 100, 400), word("b", 500, 900)] }]),
+            json!([]),
+        );
+        result.paragraphs[0].shift_time(60_000);
+        assert_eq!(
+            times(&result),
+            [(
+                vec![60_100, 60_900],
+                vec![vec![60_100, 60_400], vec![60_500, 60_900]]
+            )]
+        );
+    }
+
+    #[test]
+    fn merge_offsets_each_part_in_order() {
+        let first = transcript(
+            json!([{ "pTime": [0, 500], "role": "1", "words": [word("a", 0, 500)] }]),
+            json!([role("1", "Speaker 1")]),
+        );
+        let second = transcript(
+            json!([
+                { "pTime": [0, 300], "role": "1", "words": [word("b", 0, 300)] },
+                { "pTime": [400, 800], "role": "2", "words": [word("c", 400, 800)] }
+            ]),
+            json!([role("1", "Renamed"), role("2", "Speaker 2")]),
+        );
+
+        let merged = TranscriptResult::merge([(first, 0), (second, 3_600_000)]);
+
+        assert_eq!(
+            times(&merged),
+            [
+                (vec![0, 500], vec![vec![0, 500]]),
+                (vec![3_600_000, 3_600_300], vec![vec![3_600_000, 3_600_300]]),
+                (vec![3_600_400, 3_600_800], vec![vec![3_600_400, 3_600_800]]),
+            ]
+        );
+        let texts: Vec<&str> = merged
+            .paragraphs
+            .iter()
+            .flat_map(|p| p.words.iter().map(|w| w.text.as_str()))
+            .collect();
+        assert_eq!(texts, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn merge_keeps_first_role_of_each_id() {
+        let first = transcript(json!([]), json!([role("1", "Speaker 1")]));
+        let second = transcript(
+            json!([]),
+            json!([role("1", "Renamed"), role("2", "Speaker 2")]),
+        );
+
+        let merged = TranscriptResult::merge([(first, 0), (second, 1_000)]);
+
+        let roles: Vec<(&str, &str)> = merged
+            .roles
+            .iter()
+            .map(|r| (r.role.as_str(), r.name.as_str()))
+            .collect();
+        assert_eq!(roles, [("1", "Speaker 1"), ("2", "Speaker 2")]);
+    }
+
+    #[test]
+    fn merge_of_nothing_is_empty() {
+        let merged = TranscriptResult::merge([]);
+        assert!(merged.paragraphs.is_empty());
+        assert!(merged.roles.is_empty());
+    }
 }
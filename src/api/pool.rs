@@ -0,0 +1,334 @@
+//! 多账号
+//!
+//! 将转录订单轮流分配到多个账号，并记录每个订单所属的账号
+
+use crate::api::client::IflyrecClient;
+use crate::api::model::*;
+use crate::error::IflyrecError;
+use crate::profile::ProfileConfig;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 账号
+#[derive(Clone)]
+pub struct Account {
+    /// 账号名称
+    pub name: String,
+    /// 该账号的客户端
+    pub client: IflyrecClient,
+    /// 该账号默认的转录选项
+    pub options: Option<TranscriptionOptions>,
+}
+
+impl Account {
+    pub fn new(name: impl Into<String>, client: IflyrecClient) -> Self {
+        Self {
+            name: name.into(),
+            client,
+            options: None,
+        }
+    }
+
+    /// 设置该账号默认的转录选项
+    pub fn options(mut self, options: TranscriptionOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+}
+
+/// 已提交的订单
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PooledOrder {
+    /// 提交订单的账号名称
+    pub account: String,
+    /// 订单ID
+    pub order_id: String,
+}
+
+/// 账号池
+///
+/// 新订单按轮询顺序分配到各账号；某个账号会话失效时自动改用下一个账号。
+/// 订单所属的账号只记录在内存中，不会持久化；新建的账号池通过 [`AccountPool::locate`]
+/// 重新查找订单所属的账号。
+pub struct AccountPool {
+    accounts: Vec<Account>,
+    next: AtomicUsize,
+    /// 订单ID到账号下标的映射，仅保存在内存中
+    owners: Mutex<HashMap<String, usize>>,
+}
+
+impl AccountPool {
+    /// 创建账号池，账号名称不能为空或重复
+    pub fn new(accounts: Vec<Account>) -> Result<Self, IflyrecError> {
+        if accounts.is_empty() {
+            return Err(IflyrecError::InvalidParameter(
+                "account pool requires at least one account".to_string(),
+            ));
+        }
+        for (i, account) in accounts.iter().enumerate() {
+            if accounts[..i].iter().any(|other| other.name == account.name) {
+                return Err(IflyrecError::InvalidParameter(format!(
+                    "duplicate account name: {}",
+                    account.name
+                )));
+            }
+        }
+        Ok(Self {
+            accounts,
+            next: AtomicUsize::new(0),
+            owners: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// 由配置文件中的所有配置创建账号池
+    ///
+    /// 无法获取会话的配置记录警告后跳过，所有配置均失败时返回最后一个错误
+    pub fn from_profiles(config: &ProfileConfig) -> Result<Self, IflyrecError> {
+        let mut accounts = Vec::new();
+        let mut last_error = None;
+        for (name, profile) in &config.profiles {
+            match profile.client() {
+                Ok(client) => accounts.push(Account {
+                    name: name.clone(),
+                    client,
+                    options: profile.options.clone(),
+                }),
+                Err(e) => {
+                    tracing::warn!("跳过账号 {name}：{e}");
+                    last_error = Some(e);
+                }
+            }
+        }
+        match last_error {
+            Some(e) if accounts.is_empty() => Err(e),
+            _ => Self::new(accounts),
+        }
+    }
+
+    /// 所有账号
+    pub fn accounts(&self) -> &[Account] {
+        &self.accounts
+    }
+
+    /// 按名称查找账号
+    pub fn account(&self, name: &str) -> Option<&Account> {
+        self.accounts.iter().find(|account| account.name == name)
+    }
+
+    /// 按轮询顺序取下一个账号
+    pub fn next_account(&self) -> &Account {
+        &self.accounts[self.next_index()]
+    }
+
+    fn next_index(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % self.accounts.len()
+    }
+
+    /// 上传音频并提交转录任务
+    ///
+    /// 未指定 `options` 时使用所选账号的默认转录选项
+    pub async fn initiate_transcription_task(
+        &self,
+        audio_path_str: &str,
+        task_name: Option<String>,
+        options: Option<TranscriptionOptions>,
+        upload_options: UploadOptions,
+    ) -> Result<PooledOrder, IflyrecError> {
+        self.submit_with(|account| {
+            account.client.initiate_transcription_task_with_options(
+                audio_path_str,
+                task_name.clone(),
+                options.clone().or_else(|| account.options.clone()),
+                upload_options.clone(),
+            )
+        })
+        .await
+    }
+
+    /// 从下一个账号开始依次提交，账号认证失败时改用下一个账号
+    async fn submit_with<'a, F, Fut>(&'a self, mut submit: F) -> Result<PooledOrder, IflyrecError>
+    where
+        F: FnMut(&'a Account) -> Fut,
+        Fut: Future<Output = Result<String, IflyrecError>>,
+    {
+        let start = self.next_index();
+        let mut last_error = None;
+        for offset in 0..self.accounts.len() {
+            let index = (start + offset) % self.accounts.len();
+            let account = &self.accounts[index];
+            match submit(account).await {
+                Ok(order_id) => {
+                    self.record_owner(&order_id, index);
+                    return Ok(PooledOrder {
+                        account: account.name.clone(),
+                        order_id,
+                    });
+                }
+                Err(e) if e.is_auth_error() => {
+                    tracing::warn!("账号 {} 无法提交订单，改用下一个账号：{e}", account.name);
+                    last_error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_error.expect("pool has at least one account"))
+    }
+
+    /// 订单所属的账号，仅包括通过本账号池提交或已定位的订单
+    pub fn owner(&self, order_id: &str) -> Option<&Account> {
+        let owners = self.owners.lock().unwrap();
+        owners.get(order_id).map(|index| &self.accounts[*index])
+    }
+
+    /// 记录订单所属的账号
+    pub fn set_owner(&self, order_id: &str, account: &str) -> Result<(), IflyrecError> {
+        let index = self
+            .accounts
+            .iter()
+            .position(|candidate| candidate.name == account)
+            .ok_or_else(|| IflyrecError::InvalidParameter(format!("unknown account: {account}")))?;
+        self.record_owner(order_id, index);
+        Ok(())
+    }
+
+    /// 查找订单所属的账号及订单
+    ///
+    /// 已知所属账号时直接查询，否则依次在各账号的订单列表中查找
+    pub async fn locate(
+        &self,
+        order_id: &str,
+    ) -> Result<(&Account, TranscriptionOrder), IflyrecError> {
+        if let Some(account) = self.owner(order_id) {
+            let order = account.client.get_order(order_id).await?;
+            return Ok((account, order));
+        }
+
+        let mut last_error = None;
+        for (index, account) in self.accounts.iter().enumerate() {
+            match account.client.get_order(order_id).await {
+                Ok(order) => {
+                    self.record_owner(order_id, index);
+                    return Ok((account, order));
+                }
+                Err(IflyrecError::OrderNotFound(_)) => {}
+                Err(e) => {
+                    tracing::warn!("在账号 {} 中查找订单失败：{e}", account.name);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| IflyrecError::OrderNotFound(order_id.to_string())))
+    }
+
+    fn record_owner(&self, order_id: &str, index: usize) {
+        let mut owners = self.owners.lock().unwrap();
+        owners.insert(order_id.to_string(), index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(names: &[&str]) -> AccountPool {
+        AccountPool::new(
+            names
+                .iter()
+                .map(|name| Account::new(*name, IflyrecClient::new(name.to_string())))
+                .collect(),
+        )
+        .unwrap()
+    }
+
+    fn names(accounts: Vec<&Account>) -> Vec<&str> {
+        accounts
+            .iter()
+            .map(|account| account.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn rejects_empty_and_duplicate_accounts() {
+        assert!(AccountPool::new(Vec::new()).is_err());
+        let client = IflyrecClient::new("x".to_string());
+        let accounts = vec![Account::new("a", client.clone()), Account::new("a", client)];
+        assert!(AccountPool::new(accounts).is_err());
+    }
+
+    #[test]
+    fn rotates_accounts_round_robin() {
+        let pool = pool(&["a", "b", "c"]);
+        let picked: Vec<_> = (0..4).map(|_| pool.next_account()).collect();
+        assert_eq!(names(picked), ["a", "b", "c", "a"]);
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_account_errors_and_records_owner() {
+        let pool = pool(&["a", "b", "c"]);
+        let mut tried = Vec::new();
+        let order = pool
+            .submit_with(|account| {
+                tried.push(account.name.clone());
+                let result = match account.name.as_str() {
+                    "a" => Err(IflyrecError::AuthError("session rejected".to_string())),
+                    name => Ok(format!("order-{name}")),
+                };
+                async move { result }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(tried, ["a", "b"]);
+        assert_eq!(
+            order,
+            PooledOrder {
+                account: "b".to_string(),
+                order_id: "order-b".to_string(),
+            }
+        );
+        assert_eq!(pool.owner("order-b").unwrap().name, "b");
+        // 下一个订单从轮询顺序中的下一个账号开始
+        assert_eq!(pool.next_account().name, "b");
+    }
+
+    #[tokio::test]
+    async fn stops_on_other_errors() {
+        let pool = pool(&["a", "b"]);
+        let mut tried = 0;
+        let err = pool
+            .submit_with(|_| {
+                tried += 1;
+                async { Err(IflyrecError::UploadError("rejected".to_string())) }
+            })
+            .await
+            .unwrap_err();
+
+        assert_eq!(tried, 1);
+        assert!(matches!(err, IflyrecError::UploadError(_)));
+    }
+
+    #[tokio::test]
+    async fn returns_last_error_when_all_accounts_fail() {
+        let pool = pool(&["a", "b"]);
+        let err = pool
+            .submit_with(|account| {
+                let err = IflyrecError::AuthError(account.name.clone());
+                async move { Err(err) }
+            })
+            .await
+            .unwrap_err();
+        assert!(matches!(err, IflyrecError::AuthError(name) if name == "b"));
+    }
+
+    #[test]
+    fn set_owner_requires_known_account() {
+        let pool = pool(&["a", "b"]);
+        pool.set_owner("1", "b").unwrap();
+        assert_eq!(pool.owner("1").unwrap().name, "b");
+        assert!(pool.owner("2").is_none());
+        assert!(pool.set_owner("1", "c").is_err());
+    }
+}
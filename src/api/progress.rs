@@ -0,0 +1,100 @@
+//! 进度报告
+//!
+//! 上传和提交转录任务的各个阶段会通过 [`ProgressReporter`] 报告进度，
+//! 可以使用闭包、`tokio::sync::mpsc` 或 `tokio::sync::watch` 通道接收进度事件。
+
+use crate::api::wait::OrderState;
+
+use std::fmt;
+use std::sync::Arc;
+use tokio::sync::{mpsc, watch};
+
+/// 进度事件
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// 正在发送元数据块以获取 `file_id`
+    MetadataHandshake,
+    /// 已获取 `file_id`，开始上传数据块
+    ///
+    /// 续传时 `uploaded_bytes` 为此前已确认的字节数
+    UploadStarted {
+        file_id: String,
+        uploaded_bytes: u64,
+        total_bytes: u64,
+        total_blocks: u64,
+    },
+    /// 数据块已被服务器确认
+    BlockUploaded {
+        block_index: u64,
+        block_bytes: u64,
+        uploaded_bytes: u64,
+        total_bytes: u64,
+    },
+    /// 所有数据块上传完成
+    UploadCompleted { file_id: String },
+    /// 正在计算服务器上的音频时长
+    CalculatingDuration,
+    /// 正在提交转录订单
+    SubmittingOrder,
+    /// 转录订单提交成功
+    OrderSubmitted { order_id: String },
+    /// 等待订单完成时订单状态发生变化
+    OrderStateChanged { order_id: String, state: OrderState },
+}
+
+/// 进度报告器
+pub trait ProgressReporter: Send + Sync {
+    /// 报告一个进度事件
+    fn report(&self, event: &ProgressEvent);
+}
+
+impl<F> ProgressReporter for F
+where
+    F: Fn(&ProgressEvent) + Send + Sync,
+{
+    fn report(&self, event: &ProgressEvent) {
+        self(event)
+    }
+}
+
+impl ProgressReporter for mpsc::UnboundedSender<ProgressEvent> {
+    fn report(&self, event: &ProgressEvent) {
+        // 接收端已关闭时忽略事件
+        let _ = self.send(event.clone());
+    }
+}
+
+impl ProgressReporter for watch::Sender<ProgressEvent> {
+    fn report(&self, event: &ProgressEvent) {
+        self.send_replace(event.clone());
+    }
+}
+
+/// 可共享的进度报告器句柄
+#[derive(Clone)]
+pub struct ProgressSink(Arc<dyn ProgressReporter>);
+
+impl ProgressSink {
+    /// 包装进度报告器
+    pub fn new(reporter: impl ProgressReporter + 'static) -> Self {
+        Self(Arc::new(reporter))
+    }
+
+    /// 报告一个进度事件
+    pub fn report(&self, event: ProgressEvent) {
+        self.0.report(&event);
+    }
+}
+
+impl fmt::Debug for ProgressSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressSink")
+    }
+}
+
+/// 向可选的进度报告器报告事件
+pub(crate) fn report(progress: Option<&ProgressSink>, event: ProgressEvent) {
+    if let Some(progress) = progress {
+        progress.report(event);
+    }
+}
@@ -0,0 +1,104 @@
+//! 订单状态
+//!
+//! 接口以字符串返回各类状态，未知取值保留在 `Unknown` 中以保持向前兼容。
+//! 接口没有公开文档，只收录实际观察到的取值：订单完成时 `orderStatus` 为 `completed`，
+//! 其余取值均保留在 `Unknown` 中
+
+use serde::Deserialize;
+use std::fmt;
+
+/// 定义与字符串互相转换的状态枚举
+macro_rules! string_enum {
+    (
+        $(#[$meta:meta])*
+        pub enum $name:ident {
+            $($(#[$variant_meta:meta])* $variant:ident => $value:literal,)*
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+        #[serde(from = "String")]
+        pub enum $name {
+            $($(#[$variant_meta])* $variant,)*
+            /// 未知状态，保留原始字符串
+            Unknown(String),
+        }
+
+        impl $name {
+            /// 接口使用的原始字符串
+            pub fn as_str(&self) -> &str {
+                match self {
+                    $(Self::$variant => $value,)*
+                    Self::Unknown(value) => value,
+                }
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                match value.as_str() {
+                    $($value => Self::$variant,)*
+                    _ => Self::Unknown(value),
+                }
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                Self::from(value.to_string())
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+string_enum! {
+    /// 订单状态（`orderStatus`）
+    pub enum OrderStatus {
+        /// 已完成，可以获取转录结果
+        Completed => "completed",
+    }
+}
+
+string_enum! {
+    /// 转录状态（`transcriptStatus`）
+    pub enum TranscriptStatus {}
+}
+
+string_enum! {
+    /// 文件状态（`hjStatus`）
+    pub enum HjStatus {}
+}
+
+string_enum! {
+    /// 文件锁定状态（`hjLockStatus`）
+    pub enum LockStatus {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_observed_values() {
+        let status: OrderStatus = serde_json::from_str(r#""completed""#).unwrap();
+        assert_eq!(status, OrderStatus::Completed);
+        assert_eq!(status.as_str(), "completed");
+    }
+
+    #[test]
+    fn keeps_unknown_values() {
+        let status: OrderStatus = serde_json::from_str(r#""transcribing""#).unwrap();
+        assert_eq!(status, OrderStatus::Unknown("transcribing".to_string()));
+        assert_eq!(status.to_string(), "transcribing");
+
+        let lock: LockStatus = serde_json::from_str(r#""1""#).unwrap();
+        assert_eq!(lock, LockStatus::from("1"));
+        assert_eq!(lock.as_str(), "1");
+    }
+}
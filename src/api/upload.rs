@@ -0,0 +1,546 @@
+//! 音频文件分块上传
+//!
+//! 上传流程分为两步：先发送 `block_index` 为 0 的元数据块获取 `file_id`，
+//! 再发送携带音频数据的数据块，最后一块将 `is_last` 置为 1。
+//! 除最后一块外的数据块可以并发上传，最后一块总是在其余数据块全部成功后发送。
+//! 指定清单路径时，上传进度会持久化到清单中，中断后可通过 [`IflyrecClient::resume_upload`] 续传。
+
+use crate::api::client::IflyrecClient;
+use crate::api::constants::*;
+use crate::api::manifest::UploadManifest;
+use crate::api::model::*;
+use crate::api::progress::{ProgressEvent, report};
+use crate::audio::probe;
+#[cfg(feature = "transcode")]
+use crate::audio::{needs_normalization, transcode_to_wav};
+use crate::error::IflyrecError;
+#[cfg(feature = "transcode")]
+use crate::util::temporary_path;
+use crate::util::{extract_task_name, generate_random_file_name};
+
+use bytes::Bytes;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use reqwest::header::CONTENT_LENGTH;
+use reqwest::{Body, Method};
+use serde::de::IgnoredAny;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::{self, File};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+/// 将数据块分为待上传的和清单中已确认的两部分，没有清单时全部待上传
+fn split_acknowledged(
+    blocks: Vec<UploadBlock>,
+    manifest: Option<&UploadManifest>,
+) -> (Vec<UploadBlock>, Vec<UploadBlock>) {
+    blocks
+        .into_iter()
+        .partition(|block| manifest.is_none_or(|m| !m.acknowledged_blocks.contains(&block.index)))
+}
+
+/// 音频数据块在文件中的位置
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct UploadBlock {
+    /// 块序号，从 1 开始
+    pub index: u64,
+    /// 块在文件中的起始偏移
+    pub offset: u64,
+    /// 块包含的字节数
+    pub len: u64,
+}
+
+/// 保存上传清单，失败时记录警告并返回 `false`
+async fn save_manifest(manifest: &UploadManifest, manifest_path: &Path) -> bool {
+    match manifest.save(manifest_path).await {
+        Ok(()) => true,
+        Err(e) => {
+            tracing::warn!(
+                "写入上传清单 {} 失败，中断后将无法续传：{e}",
+                manifest_path.display()
+            );
+            false
+        }
+    }
+}
+
+/// 删除转码生成的临时文件，失败时只记录警告
+async fn remove_temporary(path: &Path) {
+    if let Err(e) = fs::remove_file(path).await {
+        tracing::warn!("删除临时文件 {} 失败：{e}", path.display());
+    }
+}
+
+/// 按块大小划分文件
+///
+/// 空文件也会生成一个长度为 0 的数据块，以便发送 `is_last` 标记
+pub(crate) fn plan_blocks(file_size: u64, block_size: u64) -> Vec<UploadBlock> {
+    let block_count = file_size.div_ceil(block_size).max(1);
+    (0..block_count)
+        .map(|i| {
+            let offset = i * block_size;
+            UploadBlock {
+                index: i + 1,
+                offset,
+                len: block_size.min(file_size - offset),
+            }
+        })
+        .collect()
+}
+
+impl IflyrecClient {
+    /// 上传音频文件
+    ///
+    /// 将本地音频文件上传到讯飞服务器
+    pub async fn upload_audio_file(
+        &self,
+        audio_path_str: &str,
+        task_name: Option<String>,
+    ) -> Result<String, IflyrecError> {
+        self.upload_audio_file_with_options(audio_path_str, task_name, UploadOptions::default())
+            .await
+    }
+
+    /// 使用指定选项上传音频文件
+    ///
+    /// 音频按 `block_size` 分块上传，每块的响应都会被校验，失败的数据块会单独重试
+    pub async fn upload_audio_file_with_options(
+        &self,
+        audio_path_str: &str,
+        task_name: Option<String>,
+        options: UploadOptions,
+    ) -> Result<String, IflyrecError> {
+        validate_upload_options(&options)?;
+
+        let source_path = Path::new(audio_path_str);
+
+        let task_name = extract_task_name(source_path, task_name);
+
+        // 按需转码，转码结果写入临时文件
+        let preprocessed = preprocess_audio(source_path, options.preprocess).await?;
+        let audio_path = preprocessed.as_deref().unwrap_or(source_path);
+
+        let mut manifest_saved = false;
+        let result = self
+            .upload_prepared_file(
+                audio_path,
+                task_name,
+                &options,
+                preprocessed.is_some(),
+                &mut manifest_saved,
+            )
+            .await;
+
+        // 临时文件在上传成功后删除；上传失败且已写入清单时保留，以便续传
+        if let Some(path) = &preprocessed
+            && (result.is_ok() || !manifest_saved)
+        {
+            remove_temporary(path).await;
+        }
+
+        result
+    }
+
+    /// 上传预处理后的音频文件
+    ///
+    /// 写入上传清单后将 `manifest_saved` 置为 `true`
+    async fn upload_prepared_file(
+        &self,
+        audio_path: &Path,
+        task_name: String,
+        options: &UploadOptions,
+        temporary: bool,
+        manifest_saved: &mut bool,
+    ) -> Result<String, IflyrecError> {
+        // 获取文件元数据和时长
+        let file_metadata = fs::metadata(audio_path).await?;
+        let file_size = file_metadata.len();
+        let audio_info = probe(audio_path).await?;
+
+        // 创建初始元数据以获取 file_id
+        let initial_metadata = AudioMetadata::new(
+            task_name,
+            format!(
+                "{}{}",
+                DEFAULT_AUDIO_PATH_PREFIX,
+                generate_random_file_name(audio_info.format.extension())
+            ),
+            file_size,
+            audio_info.duration_ms.to_string(),
+            0,
+            0,
+            String::new(),
+        );
+
+        report(options.progress.as_ref(), ProgressEvent::MetadataHandshake);
+        let file_id = self.request_upload_file_id(&initial_metadata).await?;
+
+        let metadata = AudioMetadata {
+            file_id: file_id.clone(),
+            ..initial_metadata
+        };
+
+        // 需要续传时，在发送数据块前先记录 file_id；清单写入失败不影响上传本身
+        let manifest = match &options.manifest_path {
+            Some(manifest_path) => {
+                let manifest = UploadManifest::create(
+                    audio_path,
+                    options.block_size,
+                    metadata.clone(),
+                    temporary,
+                )
+                .await?;
+                *manifest_saved = save_manifest(&manifest, manifest_path).await;
+                manifest_saved.then_some(manifest)
+            }
+            None => None,
+        };
+
+        self.upload_pending_blocks(audio_path, &metadata, manifest, options)
+            .await?;
+
+        Ok(file_id)
+    }
+
+    /// 续传中断的上传
+    ///
+    /// 读取清单，校验本地文件未被修改后，使用原有的 `file_id` 上传尚未确认的数据块。
+    /// 清单中记录的分块大小优先于 `options.block_size`。
+    pub async fn resume_upload(
+        &self,
+        manifest_path: &Path,
+        options: UploadOptions,
+    ) -> Result<String, IflyrecError> {
+        let manifest = UploadManifest::load(manifest_path).await?;
+        let options = UploadOptions {
+            block_size: manifest.block_size,
+            manifest_path: Some(manifest_path.to_path_buf()),
+            ..options
+        };
+        validate_upload_options(&options)?;
+        manifest.verify_file().await?;
+
+        tracing::info!(
+            "续传文件 {}，已确认 {} 个数据块",
+            manifest.file_id(),
+            manifest.acknowledged_blocks.len()
+        );
+
+        let audio_path = manifest.file_path.clone();
+        let metadata = manifest.metadata.clone();
+        let temporary = manifest.temporary;
+
+        self.upload_pending_blocks(&audio_path, &metadata, Some(manifest), &options)
+            .await?;
+
+        if temporary {
+            remove_temporary(&audio_path).await;
+        }
+
+        Ok(metadata.file_id)
+    }
+
+    /// 上传清单中尚未确认的数据块
+    ///
+    /// 没有清单时上传全部数据块。每个数据块确认后都会更新清单，全部完成后删除清单。
+    /// 清单写入失败时只记录警告，并停止更新清单。
+    async fn upload_pending_blocks(
+        &self,
+        audio_path: &Path,
+        metadata: &AudioMetadata,
+        mut manifest: Option<UploadManifest>,
+        options: &UploadOptions,
+    ) -> Result<(), IflyrecError> {
+        let progress = options.progress.as_ref();
+        let total_bytes = metadata.audio_size;
+
+        let mut blocks = plan_blocks(total_bytes, options.block_size);
+        let total_blocks = blocks.len() as u64;
+        let last_block = blocks
+            .pop()
+            .expect("plan_blocks returns at least one block");
+
+        let (pending, acknowledged) = split_acknowledged(blocks, manifest.as_ref());
+        let mut uploaded_bytes: u64 = acknowledged.iter().map(|block| block.len).sum();
+
+        report(
+            progress,
+            ProgressEvent::UploadStarted {
+                file_id: metadata.file_id.clone(),
+                uploaded_bytes,
+                total_bytes,
+                total_blocks,
+            },
+        );
+        let mut report_block = |block: UploadBlock| {
+            uploaded_bytes += block.len;
+            report(
+                progress,
+                ProgressEvent::BlockUploaded {
+                    block_index: block.index,
+                    block_bytes: block.len,
+                    uploaded_bytes,
+                    total_bytes,
+                },
+            );
+        };
+
+        // 并发上传除最后一块以外的数据块
+        let mut uploads = stream::iter(pending)
+            .map(|block| async move {
+                self.upload_block_with_retry(audio_path, metadata, block, false)
+                    .await
+                    .map(|_| block)
+            })
+            .buffer_unordered(options.concurrency);
+
+        while let Some(block) = uploads.try_next().await? {
+            if let (Some(tracked), Some(manifest_path)) = (&mut manifest, &options.manifest_path) {
+                tracked.acknowledged_blocks.insert(block.index);
+                if !save_manifest(tracked, manifest_path).await {
+                    manifest = None;
+                }
+            }
+            report_block(block);
+        }
+
+        // 其余数据块全部成功后再发送最后一块
+        self.upload_block_with_retry(audio_path, metadata, last_block, true)
+            .await?;
+        report_block(last_block);
+
+        if let Some(manifest_path) = &options.manifest_path
+            && let Err(e) = fs::remove_file(manifest_path).await
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            tracing::warn!("删除上传清单 {} 失败：{e}", manifest_path.display());
+        }
+
+        report(
+            progress,
+            ProgressEvent::UploadCompleted {
+                file_id: metadata.file_id.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// 发送元数据块，获取 `file_id`
+    pub(crate) async fn request_upload_file_id(
+        &self,
+        metadata: &AudioMetadata,
+    ) -> Result<String, IflyrecError> {
+        let header_block = metadata.to_binary_block()?;
+
+        self.retry_policy()
+            .run(|| async {
+                let response = self
+                    .build_request(Method::POST, FILE_UPLOAD_PATH, "application/octet-stream")
+                    .body(header_block.clone())
+                    .send()
+                    .await?;
+                self.parse_response::<FileUploadApiBizData>(response).await
+            })
+            .await?
+            .biz
+            .map(|biz| biz.file_id)
+            .ok_or_else(|| IflyrecError::UploadError("Missing file_id in response".to_string()))
+    }
+
+    /// 上传单个数据块，失败时按重试策略重试
+    pub(crate) async fn upload_block_with_retry(
+        &self,
+        audio_path: &Path,
+        base_metadata: &AudioMetadata,
+        block: UploadBlock,
+        is_last: bool,
+    ) -> Result<(), IflyrecError> {
+        self.retry_policy()
+            .run(|| async {
+                self.upload_block(audio_path, base_metadata, block, is_last)
+                    .await
+                    .inspect_err(|e| tracing::warn!("数据块 {} 上传失败：{e}", block.index))
+            })
+            .await
+    }
+
+    /// 上传单个数据块
+    ///
+    /// 数据块由元数据头部和文件中对应区间的音频数据组成，音频数据以流的形式发送
+    pub(crate) async fn upload_block(
+        &self,
+        audio_path: &Path,
+        base_metadata: &AudioMetadata,
+        block: UploadBlock,
+        is_last: bool,
+    ) -> Result<(), IflyrecError> {
+        let block_metadata = AudioMetadata {
+            is_last: u8::from(is_last),
+            block_index: block.index,
+            ..base_metadata.clone()
+        };
+
+        let header_block = block_metadata.to_binary_block()?;
+        let content_length = header_block.len() as u64 + block.len;
+
+        // 将头部数据块与文件区间流拼接，避免将整个数据块读入内存
+        let mut audio_file = File::open(audio_path).await?;
+        audio_file.seek(SeekFrom::Start(block.offset)).await?;
+        let body_stream = stream::once(async { Ok(Bytes::from(header_block)) })
+            .chain(ReaderStream::new(audio_file.take(block.len)));
+
+        let response = self
+            .build_request(Method::POST, FILE_UPLOAD_PATH, "application/octet-stream")
+            .header(CONTENT_LENGTH, content_length)
+            .body(Body::wrap_stream(body_stream))
+            .send()
+            .await?;
+
+        // 未归类的错误补充数据块序号，已归类的错误保持原类型以便重试和切换账号
+        self.parse_response::<IgnoredAny>(response)
+            .await
+            .map_err(|e| match e {
+                IflyrecError::ApiError { code, desc } => {
+                    IflyrecError::UploadError(format!("block {}: {code} - {desc}", block.index))
+                }
+                e => e,
+            })?;
+
+        tracing::debug!("数据块 {} 上传成功", block.index);
+        Ok(())
+    }
+}
+
+/// 按预处理方式转码音频，返回转码后的临时文件路径
+#[cfg(feature = "transcode")]
+pub(crate) async fn preprocess_audio(
+    path: &Path,
+    preprocess: AudioPreprocess,
+) -> Result<Option<PathBuf>, IflyrecError> {
+    let normalize = match preprocess {
+        AudioPreprocess::None => false,
+        AudioPreprocess::Auto => needs_normalization(path).await?,
+        AudioPreprocess::Always => true,
+    };
+    if !normalize {
+        return Ok(None);
+    }
+
+    let output = temporary_path(".wav");
+    tracing::info!("转码 {} 到 {}", path.display(), output.display());
+    if let Err(e) = transcode_to_wav(path, &output).await {
+        let _ = fs::remove_file(&output).await;
+        return Err(e);
+    }
+    Ok(Some(output))
+}
+
+/// 未启用 `transcode` 特性时只支持原样上传
+#[cfg(not(feature = "transcode"))]
+pub(crate) async fn preprocess_audio(
+    _path: &Path,
+    preprocess: AudioPreprocess,
+) -> Result<Option<PathBuf>, IflyrecError> {
+    match preprocess {
+        AudioPreprocess::None => Ok(None),
+        _ => Err(IflyrecError::InvalidParameter(
+            "audio preprocessing requires the `transcode` feature".to_string(),
+        )),
+    }
+}
+
+/// 校验上传选项
+fn validate_upload_options(options: &UploadOptions) -> Result<(), IflyrecError> {
+    if options.block_size == 0 {
+        return Err(IflyrecError::InvalidParameter(
+            "block_size must be greater than 0".to_string(),
+        ));
+    }
+    if options.concurrency == 0 {
+        return Err(IflyrecError::InvalidParameter(
+            "concurrency must be greater than 0".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranges(blocks: &[UploadBlock]) -> Vec<(u64, u64, u64)> {
+        blocks
+            .iter()
+            .map(|block| (block.index, block.offset, block.len))
+            .collect()
+    }
+
+    #[test]
+    fn splits_into_full_blocks_and_remainder() {
+        let blocks = plan_blocks(10, 4);
+        assert_eq!(ranges(&blocks), [(1, 0, 4), (2, 4, 4), (3, 8, 2)]);
+    }
+
+    #[test]
+    fn exact_multiple_has_no_empty_tail() {
+        let blocks = plan_blocks(8, 4);
+        assert_eq!(ranges(&blocks), [(1, 0, 4), (2, 4, 4)]);
+    }
+
+    #[test]
+    fn small_file_is_a_single_block() {
+        let blocks = plan_blocks(3, DEFAULT_UPLOAD_BLOCK_SIZE);
+        assert_eq!(ranges(&blocks), [(1, 0, 3)]);
+    }
+
+    #[test]
+    fn empty_file_has_one_empty_block() {
+        let blocks = plan_blocks(0, 4);
+        assert_eq!(ranges(&blocks), [(1, 0, 0)]);
+    }
+
+    #[test]
+    fn blocks_cover_the_file_contiguously() {
+        let file_size = 10 * DEFAULT_UPLOAD_BLOCK_SIZE + 12_345;
+        let blocks = plan_blocks(file_size, DEFAULT_UPLOAD_BLOCK_SIZE);
+        assert_eq!(blocks.len(), 11);
+        let mut expected_offset = 0;
+        for block in &blocks {
+            assert_eq!(block.offset, expected_offset);
+            expected_offset += block.len;
+        }
+        assert_eq!(expected_offset, file_size);
+    }
+
+    #[test]
+    fn resume_skips_acknowledged_blocks() {
+        let metadata = AudioMetadata::new(
+            "task".to_string(),
+            "tjb1/audio.wav".to_string(),
+            10,
+            "1000".to_string(),
+            0,
+            0,
+            "file-id".to_string(),
+        );
+        let manifest = UploadManifest {
+            file_path: PathBuf::from("/audio.wav"),
+            file_size: 10,
+            modified: std::time::SystemTime::UNIX_EPOCH,
+            content_hash: String::new(),
+            block_size: 2,
+            metadata,
+            acknowledged_blocks: [1, 3].into(),
+            temporary: false,
+        };
+
+        let (pending, acknowledged) = split_acknowledged(plan_blocks(10, 2), Some(&manifest));
+        assert_eq!(ranges(&pending), [(2, 2, 2), (4, 6, 2), (5, 8, 2)]);
+        assert_eq!(ranges(&acknowledged), [(1, 0, 2), (3, 4, 2)]);
+
+        let (pending, acknowledged) = split_acknowledged(plan_blocks(10, 2), None);
+        assert_eq!(pending.len(), 5);
+        assert!(acknowledged.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+//! 等待订单完成
+//!
+//! 首次轮询查找完整的订单历史，此后只查看最新的几页，直至订单完成或超过截止时间。
+//! 刚提交的订单可能尚未出现在订单列表中，此时继续轮询而不是报错
+
+use crate::api::client::IflyrecClient;
+use crate::api::model::TranscriptionOrder;
+use crate::api::progress::{ProgressEvent, ProgressSink, report};
+use crate::api::status::OrderStatus;
+use crate::error::IflyrecError;
+
+use std::fmt;
+use std::future::Future;
+use tokio::time::{Duration, Instant, sleep};
+
+/// 等待选项
+#[derive(Clone, Debug)]
+pub struct WaitOptions {
+    /// 首次轮询的间隔
+    pub interval: Duration,
+    /// 轮询间隔的上限
+    pub max_interval: Duration,
+    /// 状态未变化时轮询间隔的增长倍数，为 1 时固定间隔
+    pub backoff_factor: f64,
+    /// 等待的最长时间
+    pub timeout: Duration,
+    /// 每次轮询最多查找的订单页数
+    ///
+    /// 找到订单后轮询只查看最新的几页订单，避免每次都遍历全部历史；
+    /// 订单不在这几页中时再查找一次完整的订单历史
+    pub max_pages: usize,
+    /// 订单状态变化时报告 [`ProgressEvent::OrderStateChanged`]
+    pub progress: Option<ProgressSink>,
+}
+
+impl Default for WaitOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            max_interval: Duration::from_secs(60),
+            backoff_factor: 1.5,
+            timeout: Duration::from_secs(6 * 60 * 60),
+            max_pages: 3,
+            progress: None,
+        }
+    }
+}
+
+/// 订单等待状态
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OrderState {
+    /// 订单尚未出现在订单列表中
+    NotListed,
+    /// 订单处理中
+    Processing(OrderStatus),
+    /// 订单已完成
+    Completed,
+}
+
+impl OrderState {
+    /// 由订单（或其缺失）得到等待状态
+    pub fn of(order: Option<&TranscriptionOrder>) -> Self {
+        match order {
+            None => Self::NotListed,
+            Some(order) if order.is_finished() => Self::Completed,
+            Some(order) => Self::Processing(order.order_status.clone()),
+        }
+    }
+}
+
+impl fmt::Display for OrderState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotListed => f.write_str("not listed"),
+            Self::Processing(status) => write!(f, "processing ({status})"),
+            Self::Completed => f.write_str("completed"),
+        }
+    }
+}
+
+impl IflyrecClient {
+    /// 等待订单完成
+    ///
+    /// 超过 `options.timeout` 时返回 [`IflyrecError::OrderTimeout`]。
+    /// 订单尚未出现在订单列表中或查询订单遇到暂时性错误时继续轮询直至截止时间，其他错误立即返回
+    pub async fn wait_for_completion(
+        &self,
+        order_id: &str,
+        options: WaitOptions,
+    ) -> Result<TranscriptionOrder, IflyrecError> {
+        let deadline = Instant::now() + options.timeout;
+        self.wait_until(order_id, &options, deadline).await
+    }
+
+    /// 等待订单完成，直至指定的截止时间
+    pub(crate) async fn wait_until(
+        &self,
+        order_id: &str,
+        options: &WaitOptions,
+        deadline: Instant,
+    ) -> Result<TranscriptionOrder, IflyrecError> {
+        if options.interval.is_zero() || options.backoff_factor < 1.0 {
+            return Err(IflyrecError::InvalidParameter(
+                "interval must be greater than 0 and backoff_factor at least 1".to_string(),
+            ));
+        }
+        if options.max_pages == 0 {
+            return Err(IflyrecError::InvalidParameter(
+                "max_pages must be greater than 0".to_string(),
+            ));
+        }
+
+        poll_until(order_id, options, deadline, |pages| {
+            self.find_order(order_id, pages)
+        })
+        .await
+    }
+}
+
+/// 轮询订单直至完成
+///
+/// `lookup` 按给定的页数上限查找订单。首次查找完整的订单历史；此后订单未出现时只查看最新的
+/// `max_pages` 页（新订单总在最前），订单出现过但不在最新几页中时再查找一次完整的订单历史
+async fn poll_until<F, Fut>(
+    order_id: &str,
+    options: &WaitOptions,
+    deadline: Instant,
+    mut lookup: F,
+) -> Result<TranscriptionOrder, IflyrecError>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<Option<TranscriptionOrder>, IflyrecError>>,
+{
+    let mut last_state: Option<OrderState> = None;
+    let mut interval = options.interval;
+    loop {
+        let pages = if last_state.is_none() {
+            usize::MAX
+        } else {
+            options.max_pages
+        };
+        let found = match lookup(pages).await {
+            Ok(None) if pages != usize::MAX && last_state != Some(OrderState::NotListed) => {
+                lookup(usize::MAX).await
+            }
+            found => found,
+        };
+        let order = match found {
+            Ok(order) => order,
+            Err(e) if e.is_retryable() => {
+                tracing::warn!("查询订单 {order_id} 失败，稍后重试：{e}");
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(IflyrecError::OrderTimeout {
+                        order_id: order_id.to_string(),
+                        last_state: last_state.unwrap_or(OrderState::NotListed),
+                    });
+                }
+                sleep(interval.min(deadline - now)).await;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        let state = OrderState::of(order.as_ref());
+
+        if last_state.as_ref() != Some(&state) {
+            tracing::debug!("订单 {order_id} 状态: {state}");
+            report(
+                options.progress.as_ref(),
+                ProgressEvent::OrderStateChanged {
+                    order_id: order_id.to_string(),
+                    state: state.clone(),
+                },
+            );
+            interval = options.interval;
+        } else {
+            interval = interval
+                .mul_f64(options.backoff_factor)
+                .min(options.max_interval.max(options.interval));
+        }
+
+        match (state, order) {
+            (OrderState::Completed, Some(order)) => return Ok(order),
+            (state, _) => last_state = Some(state),
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(IflyrecError::OrderTimeout {
+                order_id: order_id.to_string(),
+                last_state: last_state.unwrap_or(OrderState::NotListed),
+            });
+        }
+        sleep(interval.min(deadline - now)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options() -> WaitOptions {
+        WaitOptions {
+            interval: Duration::from_millis(5),
+            max_interval: Duration::from_millis(5),
+            timeout: Duration::from_secs(5),
+            ..WaitOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_polling_until_the_order_is_listed() {
+        let mut calls = Vec::new();
+        let order = poll_until(
+            "a",
+            &options(),
+            Instant::now() + Duration::from_secs(5),
+            |pages| {
+                calls.push(pages);
+                let listed = calls.len() > 1;
+                async move { Ok(listed.then(|| TranscriptionOrder::for_test("a", "completed"))) }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(order.order_id, "a");
+        assert_eq!(calls, [usize::MAX, 3]);
+    }
+
+    #[tokio::test]
+    async fn times_out_while_the_order_is_not_listed() {
+        let err = poll_until(
+            "a",
+            &options(),
+            Instant::now() + Duration::from_millis(20),
+            |_| async { Ok(None) },
+        )
+        .await
+        .unwrap_err();
+
+        assert!(matches!(
+            err,
+            IflyrecError::OrderTimeout {
+                last_state: OrderState::NotListed,
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn searches_full_history_when_a_seen_order_leaves_recent_pages() {
+        let mut calls = Vec::new();
+        let order = poll_until(
+            "a",
+            &options(),
+            Instant::now() + Duration::from_secs(5),
+            |pages| {
+                calls.push(pages);
+                let order = match (calls.len(), pages) {
+                    (1, _) => Some(TranscriptionOrder::for_test("a", "waiting")),
+                    (_, usize::MAX) => Some(TranscriptionOrder::for_test("a", "completed")),
+                    _ => None,
+                };
+                async move { Ok(order) }
+            },
+        )
+        .await
+        .unwrap();
+
+        assert!(order.is_finished());
+        assert_eq!(calls, [usize::MAX, 3, usize::MAX]);
+    }
+}
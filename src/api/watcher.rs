@@ -0,0 +1,576 @@
+//! 订单状态监听
+//!
+//! 由一个共享的轮询任务定期查询订单列表，并将订单状态变化广播给所有监听者，
+//! 多个监听者不会增加对订单列表接口的请求次数。
+//! 每次轮询从最新一页开始逐页查找，直至找到所有尚未结束的被监听订单、到达最后一页或达到页数上限；
+//! 到达最后一页仍未找到的已出现订单视为已删除。
+//! 查询遇到非暂时性错误（如会话失效）时轮询任务停止，所有事件流返回该错误后结束
+
+use crate::api::client::IflyrecClient;
+use crate::api::model::TranscriptionOrder;
+use crate::api::status::OrderStatus;
+use crate::error::IflyrecError;
+
+use futures_util::Stream;
+use futures_util::stream;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::{Notify, broadcast};
+use tokio::time::{Duration, sleep};
+
+/// 广播通道容量
+const CHANNEL_CAPACITY: usize = 256;
+
+/// 默认每次轮询最多查找的订单页数
+const DEFAULT_MAX_PAGES: usize = 3;
+
+/// 订单状态变化事件
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OrderEvent {
+    /// 订单出现在订单列表中
+    Submitted,
+    /// 服务器已计算出音频时长（毫秒）
+    DurationCalculated { duration_ms: i64 },
+    /// 订单状态（`orderStatus`）变化
+    StatusChanged(OrderStatus),
+    /// 订单已完成
+    Completed,
+    /// 订单已不在订单列表中（如被删除）
+    Removed,
+}
+
+impl OrderEvent {
+    /// 是否为终止事件，此后不会再有该订单的事件
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Completed | Self::Removed)
+    }
+}
+
+/// 订单状态更新
+#[derive(Clone, Debug)]
+pub struct OrderUpdate {
+    /// 订单ID
+    pub order_id: String,
+    /// 状态变化事件
+    pub event: OrderEvent,
+    /// 产生该事件时的订单快照
+    pub order: TranscriptionOrder,
+}
+
+/// 订单状态监听器
+///
+/// 创建时启动后台轮询任务，需在 Tokio 运行时中调用；
+/// 监听器及其产生的所有事件流均被丢弃后，轮询任务自动退出
+pub struct OrderWatcher {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<WatchState>,
+    sender: broadcast::Sender<Message>,
+    wake: Notify,
+}
+
+/// 轮询任务广播的消息
+#[derive(Clone)]
+enum Message {
+    /// 订单状态更新
+    Update(Box<OrderUpdate>),
+    /// 轮询任务因错误停止
+    Stopped(Arc<IflyrecError>),
+}
+
+#[derive(Default)]
+struct WatchState {
+    /// 被监听的订单及其监听者数量
+    watchers: HashMap<String, usize>,
+    /// 各订单已产生的事件
+    tracked: HashMap<String, TrackedOrder>,
+    /// 使轮询任务停止的错误
+    failure: Option<Arc<IflyrecError>>,
+}
+
+#[derive(Default)]
+struct TrackedOrder {
+    duration_calculated: bool,
+    status: Option<OrderStatus>,
+    terminal: bool,
+    history: Vec<OrderUpdate>,
+}
+
+impl TrackedOrder {
+    /// 根据最新的订单快照产生新的事件
+    fn observe(&mut self, order: &TranscriptionOrder) -> Vec<OrderEvent> {
+        let mut events = Vec::new();
+        if self.terminal {
+            return events;
+        }
+        if self.history.is_empty() {
+            events.push(OrderEvent::Submitted);
+        }
+        if !self.duration_calculated && order.audio_durations > 0 {
+            self.duration_calculated = true;
+            events.push(OrderEvent::DurationCalculated {
+                duration_ms: order.audio_durations,
+            });
+        }
+        if order.is_finished() {
+            self.terminal = true;
+            events.push(OrderEvent::Completed);
+        } else if self.status.as_ref() != Some(&order.order_status) {
+            self.status = Some(order.order_status.clone());
+            events.push(OrderEvent::StatusChanged(order.order_status.clone()));
+        }
+        events
+    }
+
+    /// 订单已不在完整的订单列表中，此前出现过且尚未结束时产生 [`OrderEvent::Removed`]
+    fn observe_missing(&mut self) -> Option<OrderEvent> {
+        if self.terminal || self.history.is_empty() {
+            return None;
+        }
+        self.terminal = true;
+        Some(OrderEvent::Removed)
+    }
+}
+
+impl OrderWatcher {
+    /// 创建监听器，每隔 `interval` 查询一次订单列表，每次最多查找最新的 3 页订单
+    ///
+    /// `interval` 为 0 时返回 [`IflyrecError::InvalidParameter`]
+    pub fn new(client: Arc<IflyrecClient>, interval: Duration) -> Result<Self, IflyrecError> {
+        Self::with_max_pages(client, interval, DEFAULT_MAX_PAGES)
+    }
+
+    /// 创建监听器，每次轮询最多查找最新的 `max_pages` 页订单
+    ///
+    /// 不在这几页中的订单视为尚未出现，不产生事件
+    pub fn with_max_pages(
+        client: Arc<IflyrecClient>,
+        interval: Duration,
+        max_pages: usize,
+    ) -> Result<Self, IflyrecError> {
+        if interval.is_zero() {
+            return Err(IflyrecError::InvalidParameter(
+                "interval must be greater than 0".to_string(),
+            ));
+        }
+        if max_pages == 0 {
+            return Err(IflyrecError::InvalidParameter(
+                "max_pages must be greater than 0".to_string(),
+            ));
+        }
+
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        let shared = Arc::new(Shared {
+            state: Mutex::new(WatchState::default()),
+            sender,
+            wake: Notify::new(),
+        });
+        tokio::spawn(poll_orders(
+            client,
+            Arc::downgrade(&shared),
+            interval,
+            max_pages,
+        ));
+        Ok(Self { shared })
+    }
+
+    /// 监听一个或多个订单的状态变化
+    ///
+    /// 已产生的事件会先重放给新的监听者；所有订单完成后事件流结束。
+    /// 轮询任务因错误停止时返回 [`IflyrecError::WatcherStopped`] 后结束
+    pub fn watch<I, S>(
+        &self,
+        order_ids: I,
+    ) -> impl Stream<Item = Result<OrderUpdate, IflyrecError>> + Send + 'static
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let order_ids: HashSet<String> = order_ids.into_iter().map(Into::into).collect();
+
+        let (receiver, replay, failure) = {
+            let mut state = self.shared.state.lock().unwrap();
+            for order_id in &order_ids {
+                *state.watchers.entry(order_id.clone()).or_default() += 1;
+            }
+            let replay: VecDeque<OrderUpdate> = order_ids
+                .iter()
+                .filter_map(|order_id| state.tracked.get(order_id))
+                .flat_map(|tracked| tracked.history.iter().cloned())
+                .collect();
+            // 持有锁期间订阅，保证重放与后续事件之间没有遗漏或重复
+            (
+                self.shared.sender.subscribe(),
+                replay,
+                state.failure.clone(),
+            )
+        };
+        self.shared.wake.notify_one();
+
+        let subscription = Subscription {
+            shared: self.shared.clone(),
+            order_ids: order_ids.iter().cloned().collect(),
+            receiver,
+            replay,
+            pending: order_ids,
+            delivered: HashMap::new(),
+            failure,
+        };
+
+        stream::unfold(subscription, |mut subscription| async move {
+            let update = subscription.next().await?;
+            Some((update, subscription))
+        })
+    }
+}
+
+/// 单个事件流的订阅状态，丢弃时注销监听
+struct Subscription {
+    shared: Arc<Shared>,
+    order_ids: Vec<String>,
+    receiver: broadcast::Receiver<Message>,
+    replay: VecDeque<OrderUpdate>,
+    /// 尚未结束的订单
+    pending: HashSet<String>,
+    /// 各订单已交付的事件数
+    delivered: HashMap<String, usize>,
+    /// 补齐事件后待返回的轮询错误
+    failure: Option<Arc<IflyrecError>>,
+}
+
+impl Subscription {
+    async fn next(&mut self) -> Option<Result<OrderUpdate, IflyrecError>> {
+        if self.pending.is_empty() {
+            return None;
+        }
+
+        let update = loop {
+            if let Some(update) = self.replay.pop_front() {
+                break update;
+            }
+            if let Some(error) = self.failure.take() {
+                self.pending.clear();
+                return Some(Err(IflyrecError::WatcherStopped(error)));
+            }
+            match self.receiver.recv().await {
+                Ok(Message::Update(update)) if self.pending.contains(&update.order_id) => {
+                    break *update;
+                }
+                Ok(Message::Update(_)) => {}
+                Ok(Message::Stopped(error)) => self.failure = Some(error),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("订单事件流落后 {skipped} 个事件，从事件历史重新同步");
+                    self.resync();
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        };
+
+        *self.delivered.entry(update.order_id.clone()).or_default() += 1;
+        if update.event.is_terminal() {
+            self.pending.remove(&update.order_id);
+        }
+        Some(Ok(update))
+    }
+
+    /// 丢弃积压的广播，从各订单的事件历史补齐尚未交付的事件
+    fn resync(&mut self) {
+        let state = self.shared.state.lock().unwrap();
+        // 持有锁期间重新订阅，保证补齐的事件与后续广播之间没有遗漏或重复
+        self.receiver = self.receiver.resubscribe();
+        for order_id in &self.pending {
+            if let Some(tracked) = state.tracked.get(order_id) {
+                let delivered = self.delivered.get(order_id).copied().unwrap_or(0);
+                self.replay
+                    .extend(tracked.history.iter().skip(delivered).cloned());
+            }
+        }
+        if self.failure.is_none() {
+            self.failure = state.failure.clone();
+        }
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        for order_id in &self.order_ids {
+            if let Some(count) = state.watchers.get_mut(order_id) {
+                *count -= 1;
+                if *count == 0 {
+                    state.watchers.remove(order_id);
+                    state.tracked.remove(order_id);
+                }
+            }
+        }
+    }
+}
+
+/// 后台轮询任务
+///
+/// 暂时性错误在下一个轮询周期重试，其他错误广播给所有监听者后停止
+async fn poll_orders(
+    client: Arc<IflyrecClient>,
+    shared: Weak<Shared>,
+    interval: Duration,
+    max_pages: usize,
+) {
+    loop {
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+
+        match poll_once(&client, &shared, max_pages).await {
+            Ok(()) => {}
+            Err(e) if e.is_retryable() => tracing::warn!("查询订单列表失败：{e}"),
+            Err(e) => {
+                tracing::warn!("查询订单列表失败，停止监听：{e}");
+                let error = Arc::new(e);
+                let mut state = shared.state.lock().unwrap();
+                state.failure = Some(error.clone());
+                // 持有锁期间广播，保证新的监听者不会同时错过错误状态和广播
+                let _ = shared.sender.send(Message::Stopped(error));
+                return;
+            }
+        }
+
+        // 等待下一个轮询周期，或有新的订单被监听
+        tokio::select! {
+            _ = sleep(interval) => {}
+            _ = shared.wake.notified() => {}
+        }
+    }
+}
+
+/// 逐页查询订单列表，直至找到所有尚未结束的被监听订单、到达最后一页或查询了 `max_pages` 页
+async fn poll_once(
+    client: &IflyrecClient,
+    shared: &Shared,
+    max_pages: usize,
+) -> Result<(), IflyrecError> {
+    let mut unseen: HashSet<String> = {
+        let state = shared.state.lock().unwrap();
+        state
+            .watchers
+            .keys()
+            .filter(|order_id| state.tracked.get(*order_id).is_none_or(|t| !t.terminal))
+            .cloned()
+            .collect()
+    };
+
+    let mut scroll = None;
+    for _ in 0..max_pages {
+        if unseen.is_empty() {
+            break;
+        }
+        let page = client.get_orders_page(scroll).await?;
+        for order in &page.orders {
+            unseen.remove(&order.order_id);
+        }
+        publish(shared, page.orders);
+        match page.next {
+            Some(next) => scroll = Some(next),
+            None => {
+                // 已查找完整的订单列表，仍未出现的订单已被删除或尚未出现
+                publish_missing(shared, &unseen);
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 对比订单快照并广播新的事件
+fn publish(shared: &Shared, orders: Vec<TranscriptionOrder>) {
+    let mut guard = shared.state.lock().unwrap();
+    let state = &mut *guard;
+    for order in orders {
+        if !state.watchers.contains_key(&order.order_id) {
+            continue;
+        }
+        let tracked = state.tracked.entry(order.order_id.clone()).or_default();
+        for event in tracked.observe(&order) {
+            let update = OrderUpdate {
+                order_id: order.order_id.clone(),
+                event,
+                order: order.clone(),
+            };
+            tracked.history.push(update.clone());
+            // 暂时没有接收者时忽略
+            let _ = shared.sender.send(Message::Update(Box::new(update)));
+        }
+    }
+}
+
+/// 为完整订单列表中已不存在的订单广播 [`OrderEvent::Removed`]
+fn publish_missing(shared: &Shared, order_ids: &HashSet<String>) {
+    let mut guard = shared.state.lock().unwrap();
+    let state = &mut *guard;
+    for order_id in order_ids {
+        let Some(tracked) = state.tracked.get_mut(order_id) else {
+            continue;
+        };
+        let Some(event) = tracked.observe_missing() else {
+            continue;
+        };
+        // 附带最后一次出现时的订单快照
+        let order = tracked.history.last().unwrap().order.clone();
+        let update = OrderUpdate {
+            order_id: order_id.clone(),
+            event,
+            order,
+        };
+        tracked.history.push(update.clone());
+        let _ = shared.sender.send(Message::Update(Box::new(update)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::StreamExt;
+
+    fn order(order_id: &str, status: &str) -> TranscriptionOrder {
+        TranscriptionOrder::for_test(order_id, status)
+    }
+
+    /// 不启动轮询任务的监听器，由测试直接发布订单快照
+    fn watcher(capacity: usize) -> OrderWatcher {
+        let (sender, _) = broadcast::channel(capacity);
+        OrderWatcher {
+            shared: Arc::new(Shared {
+                state: Mutex::new(WatchState::default()),
+                sender,
+                wake: Notify::new(),
+            }),
+        }
+    }
+
+    async fn events(
+        stream: impl Stream<Item = Result<OrderUpdate, IflyrecError>>,
+    ) -> Vec<(String, OrderEvent)> {
+        stream
+            .map(|update| {
+                let update = update.unwrap();
+                (update.order_id, update.event)
+            })
+            .collect()
+            .await
+    }
+
+    fn waiting() -> OrderEvent {
+        OrderEvent::StatusChanged(OrderStatus::from("waiting"))
+    }
+
+    #[test]
+    fn observe_reports_each_change_once() {
+        let mut tracked = TrackedOrder::default();
+        let mut snapshot = order("a", "waiting");
+        assert_eq!(
+            tracked.observe(&snapshot),
+            [OrderEvent::Submitted, waiting()]
+        );
+        // observe 不记录历史，由 publish 追加
+        tracked.history.push(OrderUpdate {
+            order_id: "a".to_string(),
+            event: OrderEvent::Submitted,
+            order: snapshot.clone(),
+        });
+        assert_eq!(tracked.observe(&snapshot), []);
+
+        snapshot.audio_durations = 60_000;
+        snapshot.order_status = OrderStatus::from("transcribing");
+        assert_eq!(
+            tracked.observe(&snapshot),
+            [
+                OrderEvent::DurationCalculated {
+                    duration_ms: 60_000
+                },
+                OrderEvent::StatusChanged(OrderStatus::from("transcribing")),
+            ]
+        );
+
+        snapshot.order_status = OrderStatus::Completed;
+        assert_eq!(tracked.observe(&snapshot), [OrderEvent::Completed]);
+        assert_eq!(tracked.observe(&snapshot), []);
+        assert_eq!(tracked.observe_missing(), None);
+    }
+
+    #[test]
+    fn observe_missing_reports_removal_of_seen_orders() {
+        let mut unseen = TrackedOrder::default();
+        assert_eq!(unseen.observe_missing(), None);
+
+        let shared = watcher(16).shared;
+        shared
+            .state
+            .lock()
+            .unwrap()
+            .watchers
+            .insert("a".to_string(), 1);
+        publish(&shared, vec![order("a", "waiting")]);
+        publish_missing(&shared, &HashSet::from(["a".to_string()]));
+        publish_missing(&shared, &HashSet::from(["a".to_string()]));
+
+        let state = shared.state.lock().unwrap();
+        let history: Vec<_> = state.tracked["a"]
+            .history
+            .iter()
+            .map(|update| update.event.clone())
+            .collect();
+        assert_eq!(
+            history,
+            [OrderEvent::Submitted, waiting(), OrderEvent::Removed]
+        );
+    }
+
+    #[tokio::test]
+    async fn late_subscriber_receives_replay() {
+        let watcher = watcher(16);
+        let _first = watcher.watch(["a"]);
+        publish(&watcher.shared, vec![order("a", "waiting")]);
+
+        let late = watcher.watch(["a"]);
+        publish(&watcher.shared, vec![order("a", "completed")]);
+
+        let a = |event| ("a".to_string(), event);
+        assert_eq!(
+            events(late).await,
+            [
+                a(OrderEvent::Submitted),
+                a(waiting()),
+                a(OrderEvent::Completed)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn lagged_subscriber_resyncs_from_history() {
+        let watcher = watcher(1);
+        let stream = watcher.watch(["a", "b"]);
+        publish(
+            &watcher.shared,
+            vec![order("a", "waiting"), order("b", "waiting")],
+        );
+        publish(
+            &watcher.shared,
+            vec![order("a", "completed"), order("b", "completed")],
+        );
+
+        let events = events(stream).await;
+        for order_id in ["a", "b"] {
+            let history: Vec<_> = events
+                .iter()
+                .filter(|(id, _)| id == order_id)
+                .map(|(_, event)| event.clone())
+                .collect();
+            assert_eq!(
+                history,
+                [OrderEvent::Submitted, waiting(), OrderEvent::Completed],
+                "{order_id}"
+            );
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! FLAC 时长解析
+
+use super::malformed;
+use crate::error::IflyrecError;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+/// STREAMINFO 元数据块类型
+const BLOCK_TYPE_STREAMINFO: u8 = 0;
+
+/// 解析 STREAMINFO 块，由采样率和总采样数计算时长（毫秒）
+pub async fn duration_ms<R>(reader: &mut R) -> Result<u64, IflyrecError>
+where
+    R: AsyncRead + Unpin,
+{
+    // "fLaC"(4) + 元数据块头(4) + STREAMINFO(34)
+    let mut header = [0u8; 42];
+    reader
+        .read_exact(&mut header)
+        .await
+        .map_err(|_| malformed("FLAC", "file too short for STREAMINFO"))?;
+    if &header[0..4] != b"fLaC" {
+        return Err(malformed("FLAC", "missing fLaC signature"));
+    }
+    if header[4] & 0x7F != BLOCK_TYPE_STREAMINFO {
+        return Err(malformed("FLAC", "first metadata block is not STREAMINFO"));
+    }
+
+    let info = &header[8..42];
+    let sample_rate =
+        (u64::from(info[10]) << 12) | (u64::from(info[11]) << 4) | (u64::from(info[12]) >> 4);
+    let total_samples = (u64::from(info[13] & 0x0F) << 32)
+        | u64::from(u32::from_be_bytes([info[14], info[15], info[16], info[17]]));
+
+    if sample_rate == 0 {
+        return Err(malformed("FLAC", "sample rate is zero"));
+    }
+    if total_samples == 0 {
+        return Err(malformed("FLAC", "total sample count is unknown"));
+    }
+
+    Ok(total_samples * 1000 / sample_rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 构造只含 STREAMINFO 的 FLAC 头部
+    fn flac_header(block_type: u8, sample_rate: u32, total_samples: u64) -> Vec<u8> {
+        let mut info = [0u8; 34];
+        info[10] = (sample_rate >> 12) as u8;
+        info[11] = (sample_rate >> 4) as u8;
+        // 低 4 位为声道数和位深的高位
+        info[12] = ((sample_rate & 0x0F) << 4) as u8 | 0x02;
+        info[13] = 0xF0 | (total_samples >> 32) as u8;
+        info[14..18].copy_from_slice(&(total_samples as u32).to_be_bytes());
+
+        let mut header = b"fLaC".to_vec();
+        header.extend_from_slice(&[0x80 | block_type, 0, 0, 34]);
+        header.extend_from_slice(&info);
+        header
+    }
+
+    #[tokio::test]
+    async fn reads_streaminfo_duration() {
+        let header = flac_header(BLOCK_TYPE_STREAMINFO, 44_100, 441_000);
+        assert_eq!(duration_ms(&mut header.as_slice()).await.unwrap(), 10_000);
+    }
+
+    #[tokio::test]
+    async fn reads_36_bit_sample_count() {
+        let header = flac_header(BLOCK_TYPE_STREAMINFO, 96_000, 0x1_0000_0000);
+        assert_eq!(
+            duration_ms(&mut header.as_slice()).await.unwrap(),
+            0x1_0000_0000 * 1000 / 96_000
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_headers() {
+        for header in [
+            flac_header(BLOCK_TYPE_STREAMINFO, 44_100, 441_000)[..20].to_vec(),
+            flac_header(4, 44_100, 441_000),
+            flac_header(BLOCK_TYPE_STREAMINFO, 0, 441_000),
+            flac_header(BLOCK_TYPE_STREAMINFO, 44_100, 0),
+        ] {
+            assert!(matches!(
+                duration_ms(&mut header.as_slice()).await,
+                Err(IflyrecError::DurationCalculationError(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! 音频探测
+//!
+//! 通过文件头部的魔数识别音频格式，并解析格式相关的头部信息以获取准确的音频时长
+
+mod flac;
+mod mp3;
+mod mp4;
+mod ogg;
+mod split;
+#[cfg(feature = "transcode")]
+mod transcode;
+mod wav;
+
+pub use split::{AudioSegment, SplitOptions, split_wav};
+#[cfg(feature = "transcode")]
+pub use transcode::{TARGET_SAMPLE_RATE, needs_normalization, transcode_to_wav};
+
+use crate::error::IflyrecError;
+
+use std::path::Path;
+use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// 用于识别格式的头部字节数
+const DETECT_HEADER_LEN: usize = 64;
+
+/// 音频格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Mp3,
+    Flac,
+    OggVorbis,
+    OggOpus,
+    /// MP4 容器（M4A/AAC）
+    Mp4,
+    /// 无法识别的格式
+    Unknown,
+}
+
+impl AudioFormat {
+    /// 根据文件头部字节识别格式
+    pub fn detect(header: &[u8]) -> Self {
+        if header.len() >= 12 && &header[0..4] == b"RIFF" && &header[8..12] == b"WAVE" {
+            return Self::Wav;
+        }
+        if header.starts_with(b"fLaC") {
+            return Self::Flac;
+        }
+        if header.starts_with(b"OggS") {
+            return ogg::detect_codec(header);
+        }
+        if header.len() >= 8 && &header[4..8] == b"ftyp" {
+            return Self::Mp4;
+        }
+        if header.starts_with(b"ID3") || mp3::FrameHeader::parse(header).is_some() {
+            return Self::Mp3;
+        }
+        Self::Unknown
+    }
+
+    /// 上传时使用的文件扩展名
+    ///
+    /// 无法识别的格式沿用 `wav`
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav | Self::Unknown => "wav",
+            Self::Mp3 => "mp3",
+            Self::Flac => "flac",
+            Self::OggVorbis => "ogg",
+            Self::OggOpus => "opus",
+            Self::Mp4 => "m4a",
+        }
+    }
+}
+
+/// 音频探测结果
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioInfo {
+    /// 音频格式
+    pub format: AudioFormat,
+    /// 时长（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 探测音频文件的格式和时长
+///
+/// 头部损坏时返回 [`IflyrecError::DurationCalculationError`]；
+/// 无法识别的格式按文件大小估算时长。
+pub async fn probe(path: &Path) -> Result<AudioInfo, IflyrecError> {
+    let mut file = File::open(path).await?;
+    let file_size = file.metadata().await?.len();
+
+    let mut header = Vec::with_capacity(DETECT_HEADER_LEN);
+    (&mut file)
+        .take(DETECT_HEADER_LEN as u64)
+        .read_to_end(&mut header)
+        .await?;
+    file.rewind().await?;
+
+    let format = AudioFormat::detect(&header);
+    let duration_ms = match format {
+        AudioFormat::Wav => wav::duration_ms(&mut file, file_size).await?,
+        AudioFormat::Mp3 => mp3::duration_ms(&mut file, file_size).await?,
+        AudioFormat::Flac => flac::duration_ms(&mut file).await?,
+        AudioFormat::OggVorbis | AudioFormat::OggOpus => {
+            ogg::duration_ms(&mut file, file_size).await?
+        }
+        AudioFormat::Mp4 => mp4::duration_ms(&mut file, file_size).await?,
+        AudioFormat::Unknown => {
+            tracing::warn!("无法识别 {} 的音频格式，按文件大小估算时长", path.display());
+            estimate_duration_ms(file_size)
+        }
+    };
+
+    Ok(AudioInfo {
+        format,
+        duration_ms,
+    })
+}
+
+/// 获取音频文件时长（毫秒）
+pub async fn probe_duration_ms(path: &Path) -> Result<u64, IflyrecError> {
+    Ok(probe(path).await?.duration_ms)
+}
+
+/// 按 16 kHz 单声道 16 位 PCM 的码率由文件大小估算时长（毫秒）
+#[inline]
+pub fn estimate_duration_ms(file_size: u64) -> u64 {
+    file_size / 32
+}
+
+fn malformed(format: &str, reason: &str) -> IflyrecError {
+    IflyrecError::DurationCalculationError(format!("Malformed {format} header: {reason}"))
+}
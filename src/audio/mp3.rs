@@ -0,0 +1,327 @@
+//! MP3 时长解析
+
+use super::malformed;
+use crate::error::IflyrecError;
+
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// 查找第一个帧头时最多扫描的字节数
+const FRAME_SCAN_LEN: usize = 64 * 1024;
+/// ID3v1 标签长度
+const ID3V1_LEN: u64 = 128;
+
+/// 比特率表（kbps），按 [MPEG-1 / MPEG-2 及 2.5][层 I, II, III] 索引
+const BITRATES: [[[u16; 15]; 3]; 2] = [
+    [
+        [
+            0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ],
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ],
+        [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ],
+    ],
+    [
+        [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+        [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160],
+    ],
+];
+
+/// MPEG-1 采样率，MPEG-2 为其一半，MPEG-2.5 为其四分之一
+const SAMPLE_RATES: [u32; 3] = [44_100, 48_000, 32_000];
+
+/// MPEG 版本
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MpegVersion {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+/// MPEG 音频帧头
+#[derive(Clone, Copy, Debug)]
+pub struct FrameHeader {
+    version: MpegVersion,
+    /// 层，取值 1~3
+    layer: u8,
+    /// 比特率（kbps），0 表示自由格式
+    bitrate_kbps: u32,
+    sample_rate: u32,
+    mono: bool,
+}
+
+impl FrameHeader {
+    /// 解析 4 字节帧头，不是有效帧头时返回 `None`
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let header = u32::from_be_bytes(bytes.get(0..4)?.try_into().ok()?);
+        if header >> 21 != 0x7FF {
+            return None;
+        }
+
+        let version = match (header >> 19) & 0b11 {
+            0b00 => MpegVersion::Mpeg25,
+            0b10 => MpegVersion::Mpeg2,
+            0b11 => MpegVersion::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (header >> 17) & 0b11 {
+            0b01 => 3,
+            0b10 => 2,
+            0b11 => 1,
+            _ => return None,
+        };
+        let bitrate_index = ((header >> 12) & 0b1111) as usize;
+        let sample_rate_index = ((header >> 10) & 0b11) as usize;
+        if bitrate_index == 0b1111 || sample_rate_index == 0b11 {
+            return None;
+        }
+
+        let table = usize::from(version != MpegVersion::Mpeg1);
+        let bitrate_kbps = u32::from(BITRATES[table][usize::from(layer - 1)][bitrate_index]);
+        let sample_rate = SAMPLE_RATES[sample_rate_index]
+            / match version {
+                MpegVersion::Mpeg1 => 1,
+                MpegVersion::Mpeg2 => 2,
+                MpegVersion::Mpeg25 => 4,
+            };
+
+        Some(Self {
+            version,
+            layer,
+            bitrate_kbps,
+            sample_rate,
+            mono: (header >> 6) & 0b11 == 0b11,
+        })
+    }
+
+    /// 每帧的采样数
+    fn samples_per_frame(&self) -> u64 {
+        match (self.layer, self.version) {
+            (1, _) => 384,
+            (3, MpegVersion::Mpeg2 | MpegVersion::Mpeg25) => 576,
+            _ => 1152,
+        }
+    }
+
+    /// Xing/Info 头相对帧起始的偏移，即帧头与边信息之后
+    fn xing_offset(&self) -> usize {
+        4 + match (self.version == MpegVersion::Mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+}
+
+/// 计算 MP3 时长（毫秒）
+///
+/// 优先使用 Xing/Info 或 VBRI 头中的总帧数（VBR 文件），否则按第一帧的比特率计算（CBR 文件）
+pub async fn duration_ms<R>(reader: &mut R, file_size: u64) -> Result<u64, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let audio_start = skip_id3v2(reader).await?;
+
+    reader.seek(SeekFrom::Start(audio_start)).await?;
+    let mut buffer = Vec::with_capacity(FRAME_SCAN_LEN);
+    (&mut *reader)
+        .take(FRAME_SCAN_LEN as u64)
+        .read_to_end(&mut buffer)
+        .await?;
+
+    let frame_pos = (0..buffer.len().saturating_sub(3))
+        .find(|&i| FrameHeader::parse(&buffer[i..]).is_some())
+        .ok_or_else(|| malformed("MP3", "no MPEG audio frame found"))?;
+    let frame = &buffer[frame_pos..];
+    let header = FrameHeader::parse(frame).expect("frame header was validated");
+    if header.sample_rate == 0 {
+        return Err(malformed("MP3", "sample rate is zero"));
+    }
+
+    if let Some(frames) = xing_frames(frame, &header).or_else(|| vbri_frames(frame)) {
+        return Ok(frames * header.samples_per_frame() * 1000 / u64::from(header.sample_rate));
+    }
+
+    if header.bitrate_kbps == 0 {
+        return Err(malformed("MP3", "free-format bitrate without VBR header"));
+    }
+
+    // CBR：扣除 ID3v2 标签、帧前的无效数据和 ID3v1 标签
+    let mut audio_end = file_size;
+    if file_size >= ID3V1_LEN {
+        reader.seek(SeekFrom::Start(file_size - ID3V1_LEN)).await?;
+        let mut tag = [0u8; 3];
+        reader.read_exact(&mut tag).await?;
+        if &tag == b"TAG" {
+            audio_end -= ID3V1_LEN;
+        }
+    }
+    let audio_bytes = audio_end.saturating_sub(audio_start + frame_pos as u64);
+
+    Ok(audio_bytes * 8 / u64::from(header.bitrate_kbps))
+}
+
+/// 跳过 ID3v2 标签，返回音频数据的起始偏移
+async fn skip_id3v2<R>(reader: &mut R) -> Result<u64, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut header = [0u8; 10];
+    if reader.read_exact(&mut header).await.is_err() || &header[0..3] != b"ID3" {
+        return Ok(0);
+    }
+
+    // 标签长度使用 synchsafe 整数，每字节仅低 7 位有效
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |acc, &b| (acc << 7) | u64::from(b & 0x7F));
+    let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+
+    Ok(10 + size + footer)
+}
+
+/// 读取 Xing/Info 头中的总帧数
+fn xing_frames(frame: &[u8], header: &FrameHeader) -> Option<u64> {
+    let offset = header.xing_offset();
+    let tag = frame.get(offset..offset + 4)?;
+    if tag != b"Xing" && tag != b"Info" {
+        return None;
+    }
+
+    let flags = u32::from_be_bytes(frame.get(offset + 4..offset + 8)?.try_into().ok()?);
+    if flags & 0x1 == 0 {
+        return None;
+    }
+    let frames = u32::from_be_bytes(frame.get(offset + 8..offset + 12)?.try_into().ok()?);
+    Some(u64::from(frames))
+}
+
+/// 读取 VBRI 头中的总帧数，VBRI 头固定位于帧头后 32 字节处
+fn vbri_frames(frame: &[u8]) -> Option<u64> {
+    const OFFSET: usize = 4 + 32;
+    if frame.get(OFFSET..OFFSET + 4)? != b"VBRI" {
+        return None;
+    }
+    // id(4) + version(2) + delay(2) + quality(2) + bytes(4)
+    let frames = u32::from_be_bytes(frame.get(OFFSET + 14..OFFSET + 18)?.try_into().ok()?);
+    Some(u64::from(frames))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// MPEG-1 层 III，128 kbps，44.1 kHz，立体声
+    const MPEG1_STEREO: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+    /// MPEG-1 层 III，128 kbps，44.1 kHz，单声道
+    const MPEG1_MONO: [u8; 4] = [0xFF, 0xFB, 0x90, 0xC0];
+    /// MPEG-2 层 III，64 kbps，22.05 kHz，立体声
+    const MPEG2_STEREO: [u8; 4] = [0xFF, 0xF3, 0x80, 0x00];
+
+    /// 构造首帧，在 `offset` 处写入 VBR 头
+    fn frame(header: [u8; 4], offset: usize, vbr: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 417];
+        frame[0..4].copy_from_slice(&header);
+        frame[offset..offset + vbr.len()].copy_from_slice(vbr);
+        frame
+    }
+
+    fn xing(tag: &[u8; 4], frames: u32) -> Vec<u8> {
+        let mut data = tag.to_vec();
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&frames.to_be_bytes());
+        data
+    }
+
+    async fn duration(bytes: &[u8]) -> Result<u64, IflyrecError> {
+        duration_ms(&mut Cursor::new(bytes), bytes.len() as u64).await
+    }
+
+    #[test]
+    fn parses_frame_headers() {
+        let header = FrameHeader::parse(&MPEG1_STEREO).unwrap();
+        assert_eq!(header.version, MpegVersion::Mpeg1);
+        assert_eq!(header.layer, 3);
+        assert_eq!(header.bitrate_kbps, 128);
+        assert_eq!(header.sample_rate, 44_100);
+        assert_eq!(header.samples_per_frame(), 1152);
+
+        let header = FrameHeader::parse(&MPEG2_STEREO).unwrap();
+        assert_eq!(header.version, MpegVersion::Mpeg2);
+        assert_eq!(header.bitrate_kbps, 64);
+        assert_eq!(header.sample_rate, 22_050);
+        assert_eq!(header.samples_per_frame(), 576);
+    }
+
+    #[test]
+    fn rejects_invalid_frame_headers() {
+        assert!(FrameHeader::parse(b"ID3\x04").is_none());
+        // 保留的版本、层、比特率与采样率
+        assert!(FrameHeader::parse(&[0xFF, 0xEB, 0x90, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xF9, 0x90, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0x9C, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xFB]).is_none());
+    }
+
+    #[tokio::test]
+    async fn cbr_duration_excludes_tags() {
+        // 10 字节 ID3v2 头部 + 100 字节标签内容
+        let mut file = b"ID3\x04\x00\x00\x00\x00\x00\x64".to_vec();
+        file.extend_from_slice(&[0; 100]);
+        let mut audio = vec![0u8; 32_000];
+        audio[0..4].copy_from_slice(&MPEG1_STEREO);
+        file.extend_from_slice(&audio);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, 0);
+        file.extend_from_slice(&id3v1);
+
+        // 32000 字节 * 8 / 128 kbps
+        assert_eq!(duration(&file).await.unwrap(), 2000);
+    }
+
+    #[tokio::test]
+    async fn reads_xing_frame_count() {
+        let file = frame(MPEG1_STEREO, 36, &xing(b"Xing", 100));
+        assert_eq!(duration(&file).await.unwrap(), 100 * 1152 * 1000 / 44_100);
+
+        let file = frame(MPEG1_MONO, 21, &xing(b"Info", 100));
+        assert_eq!(duration(&file).await.unwrap(), 100 * 1152 * 1000 / 44_100);
+
+        let file = frame(MPEG2_STEREO, 21, &xing(b"Xing", 100));
+        assert_eq!(duration(&file).await.unwrap(), 100 * 576 * 1000 / 22_050);
+    }
+
+    #[tokio::test]
+    async fn reads_vbri_frame_count() {
+        let mut vbri = b"VBRI".to_vec();
+        vbri.extend_from_slice(&[0; 10]);
+        vbri.extend_from_slice(&500u32.to_be_bytes());
+        let file = frame(MPEG1_STEREO, 36, &vbri);
+        assert_eq!(duration(&file).await.unwrap(), 500 * 1152 * 1000 / 44_100);
+    }
+
+    #[tokio::test]
+    async fn skips_garbage_before_first_frame() {
+        let mut file = vec![0x55; 7];
+        let mut audio = vec![0u8; 16_000];
+        audio[0..4].copy_from_slice(&MPEG1_STEREO);
+        file.extend_from_slice(&audio);
+        assert_eq!(duration(&file).await.unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    async fn missing_frame_is_malformed() {
+        assert!(matches!(
+            duration(&[0; 1024]).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+}
@@ -0,0 +1,305 @@
+//! MP4/M4A 时长解析
+
+use super::malformed;
+use crate::error::IflyrecError;
+
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// box 在文件中的位置
+#[derive(Clone, Copy, Debug)]
+struct BoxRange {
+    /// box 内容的起始偏移
+    body_start: u64,
+    /// box 的结束偏移
+    end: u64,
+}
+
+/// 由 `mvhd` 计算时长（毫秒），`mvhd` 缺失或时长为 0 时使用第一个轨道的 `mdhd`
+pub async fn duration_ms<R>(reader: &mut R, file_size: u64) -> Result<u64, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let moov = find_box(reader, 0, file_size, b"moov")
+        .await?
+        .ok_or_else(|| malformed("MP4", "missing moov box"))?;
+
+    if let Some(mvhd) = find_box(reader, moov.body_start, moov.end, b"mvhd").await?
+        && let Some(duration) = read_header_duration(reader, mvhd).await?
+    {
+        return Ok(duration);
+    }
+
+    let mut range = moov;
+    for box_type in [b"trak", b"mdia", b"mdhd"] {
+        range = find_box(reader, range.body_start, range.end, box_type)
+            .await?
+            .ok_or_else(|| malformed("MP4", "missing mvhd and mdhd boxes"))?;
+    }
+
+    read_header_duration(reader, range)
+        .await?
+        .ok_or_else(|| malformed("MP4", "duration is unknown"))
+}
+
+/// 在 `[start, end)` 范围内查找指定类型的 box
+async fn find_box<R>(
+    reader: &mut R,
+    start: u64,
+    end: u64,
+    box_type: &[u8; 4],
+) -> Result<Option<BoxRange>, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut offset = start;
+    while offset + 8 <= end {
+        reader.seek(SeekFrom::Start(offset)).await?;
+        let size = u64::from(reader.read_u32().await.map_err(truncated)?);
+        let mut current_type = [0u8; 4];
+        reader
+            .read_exact(&mut current_type)
+            .await
+            .map_err(truncated)?;
+
+        let (header_len, box_size) = match size {
+            // size 为 1 时使用 64 位长度
+            1 if offset + 16 > end => return Err(malformed("MP4", "truncated box header")),
+            1 => (16, reader.read_u64().await.map_err(truncated)?),
+            // size 为 0 时 box 延伸到范围末尾
+            0 => (8, end - offset),
+            size => (8, size),
+        };
+        let box_end = offset
+            .checked_add(box_size)
+            .filter(|&box_end| box_size >= header_len && box_end <= end)
+            .ok_or_else(|| malformed("MP4", "invalid box size"))?;
+
+        if &current_type == box_type {
+            return Ok(Some(BoxRange {
+                body_start: offset + header_len,
+                end: box_end,
+            }));
+        }
+        offset = box_end;
+    }
+    Ok(None)
+}
+
+/// 读取 `mvhd`/`mdhd` 中的时间尺度和时长，时长未知时返回 `None`
+async fn read_header_duration<R>(
+    reader: &mut R,
+    header: BoxRange,
+) -> Result<Option<u64>, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let body_len = header.end - header.body_start;
+    if body_len < 4 {
+        return Err(malformed("MP4", "truncated header box"));
+    }
+    reader.seek(SeekFrom::Start(header.body_start)).await?;
+    let version = reader.read_u8().await.map_err(truncated)?;
+    let mut flags = [0u8; 3];
+    reader.read_exact(&mut flags).await.map_err(truncated)?;
+
+    // version(1) + flags(3) + 创建和修改时间 + timescale(4) + duration
+    let required = if version == 1 { 32 } else { 20 };
+    if body_len < required {
+        return Err(malformed("MP4", "truncated header box"));
+    }
+
+    let (timescale, duration) = if version == 1 {
+        // creation_time(8) + modification_time(8)
+        reader.seek(SeekFrom::Current(16)).await?;
+        (
+            reader.read_u32().await.map_err(truncated)?,
+            reader.read_u64().await.map_err(truncated)?,
+        )
+    } else {
+        // creation_time(4) + modification_time(4)
+        reader.seek(SeekFrom::Current(8)).await?;
+        let timescale = reader.read_u32().await.map_err(truncated)?;
+        let duration = reader.read_u32().await.map_err(truncated)?;
+        // 全 1 表示时长未知
+        let duration = if duration == u32::MAX {
+            0
+        } else {
+            u64::from(duration)
+        };
+        (timescale, duration)
+    };
+
+    if timescale == 0 || duration == 0 || duration == u64::MAX {
+        return Ok(None);
+    }
+    let duration_ms = duration
+        .checked_mul(1000)
+        .ok_or_else(|| malformed("MP4", "duration overflows"))?
+        / u64::from(timescale);
+    Ok(Some(duration_ms))
+}
+
+/// 读取 box 时遇到文件结尾说明文件被截断
+fn truncated(error: std::io::Error) -> IflyrecError {
+    if error.kind() == std::io::ErrorKind::UnexpectedEof {
+        malformed("MP4", "truncated box")
+    } else {
+        IflyrecError::IoError(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 构造一个 box
+    fn mp4_box(box_type: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut data = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(box_type);
+        data.extend_from_slice(body);
+        data
+    }
+
+    /// 构造版本 0 的 `mvhd`/`mdhd` 内容
+    fn header_v0(timescale: u32, duration: u32) -> Vec<u8> {
+        let mut body = vec![0; 12];
+        body.extend_from_slice(&timescale.to_be_bytes());
+        body.extend_from_slice(&duration.to_be_bytes());
+        body
+    }
+
+    /// 构造版本 1 的 `mvhd`/`mdhd` 内容
+    fn header_v1(timescale: u32, duration: u64) -> Vec<u8> {
+        let mut body = vec![1, 0, 0, 0];
+        body.extend_from_slice(&[0; 16]);
+        body.extend_from_slice(&timescale.to_be_bytes());
+        body.extend_from_slice(&duration.to_be_bytes());
+        body
+    }
+
+    fn ftyp() -> Vec<u8> {
+        mp4_box(b"ftyp", b"M4A \0\0\0\0")
+    }
+
+    async fn duration(bytes: &[u8]) -> Result<u64, IflyrecError> {
+        duration_ms(&mut Cursor::new(bytes), bytes.len() as u64).await
+    }
+
+    #[tokio::test]
+    async fn reads_mvhd_duration() {
+        let file = [
+            ftyp(),
+            mp4_box(b"moov", &mp4_box(b"mvhd", &header_v0(600, 1500))),
+        ]
+        .concat();
+        assert_eq!(duration(&file).await.unwrap(), 2500);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_mdhd_when_mvhd_is_unknown() {
+        let mdhd = mp4_box(b"mdhd", &header_v1(44_100, 441_000));
+        let trak = mp4_box(b"trak", &mp4_box(b"mdia", &mdhd));
+        let moov = mp4_box(
+            b"moov",
+            &[mp4_box(b"mvhd", &header_v0(1000, u32::MAX)), trak].concat(),
+        );
+        let file = [ftyp(), moov].concat();
+        assert_eq!(duration(&file).await.unwrap(), 10_000);
+    }
+
+    #[tokio::test]
+    async fn skips_largesize_boxes() {
+        let mut mdat = 1u32.to_be_bytes().to_vec();
+        mdat.extend_from_slice(b"mdat");
+        mdat.extend_from_slice(&32u64.to_be_bytes());
+        mdat.extend_from_slice(&[0; 16]);
+        let moov = mp4_box(b"moov", &mp4_box(b"mvhd", &header_v0(1000, 750)));
+        let file = [ftyp(), mdat, moov].concat();
+        assert_eq!(duration(&file).await.unwrap(), 750);
+    }
+
+    #[tokio::test]
+    async fn zero_size_box_extends_to_end() {
+        let mut moov = mp4_box(b"moov", &mp4_box(b"mvhd", &header_v0(1000, 42)));
+        moov[0..4].copy_from_slice(&0u32.to_be_bytes());
+        let file = [ftyp(), moov].concat();
+        assert_eq!(duration(&file).await.unwrap(), 42);
+    }
+
+    #[tokio::test]
+    async fn overflowing_largesize_is_malformed() {
+        let mut free = 1u32.to_be_bytes().to_vec();
+        free.extend_from_slice(b"free");
+        free.extend_from_slice(&u64::MAX.to_be_bytes());
+        let file = [ftyp(), free].concat();
+        assert!(matches!(
+            duration(&file).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn overflowing_duration_is_malformed() {
+        let moov = mp4_box(b"moov", &mp4_box(b"mvhd", &header_v1(1000, u64::MAX - 1)));
+        let file = [ftyp(), moov].concat();
+        assert!(matches!(
+            duration(&file).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn missing_moov_is_malformed() {
+        let file = [ftyp(), mp4_box(b"mdat", &[0; 8])].concat();
+        assert!(matches!(
+            duration(&file).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn truncated_boxes_are_malformed() {
+        let mvhd = mp4_box(b"mvhd", &header_v0(600, 1500));
+        let short_mvhd = mp4_box(b"mvhd", &header_v0(600, 1500)[..10]);
+        let short_mdhd = mp4_box(b"mdhd", &header_v1(600, 1500)[..24]);
+        let trak = mp4_box(b"trak", &mp4_box(b"mdia", &short_mdhd));
+        let complete = [ftyp(), mp4_box(b"moov", &mvhd)].concat();
+
+        let files = [
+            // 文件在 moov 中间结束
+            complete[..complete.len() - 6].to_vec(),
+            // mvhd 内容不足
+            [ftyp(), mp4_box(b"moov", &short_mvhd)].concat(),
+            // mvhd 缺失，mdhd 内容不足
+            [ftyp(), mp4_box(b"moov", &trak)].concat(),
+            // 64 位长度不完整
+            [ftyp(), 1u32.to_be_bytes().to_vec(), b"free".to_vec()].concat(),
+        ];
+        for file in files {
+            assert!(
+                matches!(
+                    duration(&file).await,
+                    Err(IflyrecError::DurationCalculationError(_))
+                ),
+                "{file:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn short_reads_are_malformed() {
+        // 调用方给出的文件大小大于实际内容
+        let file = [
+            ftyp(),
+            mp4_box(b"moov", &mp4_box(b"mvhd", &header_v0(600, 1500))),
+        ]
+        .concat();
+        let result = duration_ms(&mut Cursor::new(&file[..30]), file.len() as u64).await;
+        assert!(matches!(
+            result,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+}
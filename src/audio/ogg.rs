@@ -0,0 +1,206 @@
+//! Ogg Vorbis/Opus 时长解析
+
+use super::{AudioFormat, malformed};
+use crate::error::IflyrecError;
+
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// Ogg 页头固定部分长度
+const PAGE_HEADER_LEN: usize = 27;
+/// 查找最后一页时从文件末尾读取的字节数
+const TAIL_SCAN_LEN: u64 = 64 * 1024;
+/// Opus 的粒度位置始终以 48 kHz 计
+const OPUS_GRANULE_RATE: u64 = 48_000;
+
+/// 根据第一页的首个数据包识别编码格式
+pub fn detect_codec(header: &[u8]) -> AudioFormat {
+    match first_packet(header) {
+        Some(packet) if packet.starts_with(b"\x01vorbis") => AudioFormat::OggVorbis,
+        Some(packet) if packet.starts_with(b"OpusHead") => AudioFormat::OggOpus,
+        _ => AudioFormat::Unknown,
+    }
+}
+
+/// 由最后一页的粒度位置计算时长（毫秒）
+pub async fn duration_ms<R>(reader: &mut R, file_size: u64) -> Result<u64, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut first_page = vec![0u8; 512.min(file_size as usize)];
+    reader
+        .read_exact(&mut first_page)
+        .await
+        .map_err(|_| malformed("Ogg", "file too short"))?;
+    let serial = page_serial(&first_page).ok_or_else(|| malformed("Ogg", "missing OggS page"))?;
+    let packet = first_packet(&first_page)
+        .ok_or_else(|| malformed("Ogg", "truncated identification header"))?;
+
+    // (粒度位置的采样率, 需要扣除的预跳过采样数)
+    let (rate, pre_skip) = if packet.starts_with(b"\x01vorbis") && packet.len() >= 16 {
+        let rate = u32::from_le_bytes(packet[12..16].try_into().unwrap());
+        (u64::from(rate), 0)
+    } else if packet.starts_with(b"OpusHead") && packet.len() >= 12 {
+        let pre_skip = u16::from_le_bytes(packet[10..12].try_into().unwrap());
+        (OPUS_GRANULE_RATE, u64::from(pre_skip))
+    } else {
+        return Err(malformed("Ogg", "unsupported codec"));
+    };
+    if rate == 0 {
+        return Err(malformed("Ogg", "sample rate is zero"));
+    }
+
+    let tail_start = file_size.saturating_sub(TAIL_SCAN_LEN);
+    reader.seek(SeekFrom::Start(tail_start)).await?;
+    let mut tail = Vec::with_capacity((file_size - tail_start) as usize);
+    reader.read_to_end(&mut tail).await?;
+
+    let granule = last_granule(&tail, serial)
+        .ok_or_else(|| malformed("Ogg", "no page with a valid granule position"))?;
+
+    let duration_ms = granule
+        .saturating_sub(pre_skip)
+        .checked_mul(1000)
+        .ok_or_else(|| malformed("Ogg", "granule position overflows"))?
+        / rate;
+    Ok(duration_ms)
+}
+
+/// 读取页头中的流序列号
+fn page_serial(page: &[u8]) -> Option<u32> {
+    if page.len() < PAGE_HEADER_LEN || !page.starts_with(b"OggS") {
+        return None;
+    }
+    Some(u32::from_le_bytes(page[14..18].try_into().unwrap()))
+}
+
+/// 取出页中第一个数据包的内容（可能被截断）
+fn first_packet(page: &[u8]) -> Option<&[u8]> {
+    let segment_count = usize::from(*page.get(26)?);
+    let data_start = PAGE_HEADER_LEN + segment_count;
+    let segment_table = page.get(PAGE_HEADER_LEN..data_start)?;
+
+    // 数据包在遇到长度小于 255 的分段时结束
+    let mut packet_len = 0;
+    for &lacing in segment_table {
+        packet_len += usize::from(lacing);
+        if lacing < 255 {
+            break;
+        }
+    }
+
+    let data = page.get(data_start..)?;
+    Some(&data[..packet_len.min(data.len())])
+}
+
+/// 在文件尾部数据中查找指定流最后一页的粒度位置
+fn last_granule(tail: &[u8], serial: u32) -> Option<u64> {
+    let mut end = tail.len();
+    while let Some(pos) = tail[..end].windows(4).rposition(|w| w == b"OggS") {
+        let page = &tail[pos..];
+        if page_serial(page) == Some(serial) {
+            let granule = i64::from_le_bytes(page[6..14].try_into().unwrap());
+            // -1 表示该页没有结束任何数据包
+            if granule >= 0 {
+                return Some(granule as u64);
+            }
+        }
+        end = pos;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 构造只含一个数据包的 Ogg 页
+    fn page(serial: u32, granule: i64, packet: &[u8]) -> Vec<u8> {
+        let mut page = b"OggS\0\0".to_vec();
+        page.extend_from_slice(&granule.to_le_bytes());
+        page.extend_from_slice(&serial.to_le_bytes());
+        page.extend_from_slice(&[0; 8]);
+        let mut lacing = vec![255; packet.len() / 255];
+        lacing.push((packet.len() % 255) as u8);
+        page.push(lacing.len() as u8);
+        page.extend_from_slice(&lacing);
+        page.extend_from_slice(packet);
+        page
+    }
+
+    fn vorbis_id(sample_rate: u32) -> Vec<u8> {
+        let mut packet = b"\x01vorbis".to_vec();
+        packet.extend_from_slice(&0u32.to_le_bytes());
+        packet.push(2);
+        packet.extend_from_slice(&sample_rate.to_le_bytes());
+        packet.resize(30, 0);
+        packet
+    }
+
+    fn opus_head(pre_skip: u16) -> Vec<u8> {
+        let mut packet = b"OpusHead\x01\x01".to_vec();
+        packet.extend_from_slice(&pre_skip.to_le_bytes());
+        packet.extend_from_slice(&48_000u32.to_le_bytes());
+        packet.resize(19, 0);
+        packet
+    }
+
+    async fn duration(bytes: &[u8]) -> Result<u64, IflyrecError> {
+        duration_ms(&mut Cursor::new(bytes), bytes.len() as u64).await
+    }
+
+    #[test]
+    fn detects_codec_from_first_packet() {
+        assert_eq!(
+            detect_codec(&page(1, 0, &vorbis_id(44_100))),
+            AudioFormat::OggVorbis
+        );
+        assert_eq!(
+            detect_codec(&page(1, 0, &opus_head(312))),
+            AudioFormat::OggOpus
+        );
+        assert_eq!(
+            detect_codec(&page(1, 0, b"\x80theora")),
+            AudioFormat::Unknown
+        );
+    }
+
+    #[tokio::test]
+    async fn vorbis_duration_uses_sample_rate() {
+        let file = [
+            page(7, 0, &vorbis_id(44_100)),
+            page(7, 44_100, &[0; 300]),
+            page(7, 88_200, &[0; 100]),
+        ]
+        .concat();
+        assert_eq!(duration(&file).await.unwrap(), 2000);
+    }
+
+    #[tokio::test]
+    async fn opus_duration_subtracts_pre_skip() {
+        let file = [page(7, 0, &opus_head(312)), page(7, 96_312, &[0; 100])].concat();
+        assert_eq!(duration(&file).await.unwrap(), 2000);
+    }
+
+    #[tokio::test]
+    async fn ignores_other_streams_and_unfinished_pages() {
+        let file = [
+            page(7, 0, &vorbis_id(8_000)),
+            page(7, 8_000, &[0; 10]),
+            page(9, 800_000, &[0; 10]),
+            page(7, -1, &[0; 10]),
+        ]
+        .concat();
+        assert_eq!(duration(&file).await.unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    async fn overflowing_granule_is_malformed() {
+        let file = [page(7, 0, &vorbis_id(8_000)), page(7, i64::MAX, &[0; 10])].concat();
+        assert!(matches!(
+            duration(&file).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+}
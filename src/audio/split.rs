@@ -0,0 +1,338 @@
+//! 长音频分段
+//!
+//! 将未压缩（整数 PCM 或 IEEE 浮点）WAV 文件切分为多个不超过指定时长的分段，切分点优先选在静音处
+
+use super::wav::{WavLayout, read_layout, wav_header};
+use crate::error::IflyrecError;
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufWriter};
+
+/// 静音检测的分析窗口长度（毫秒）
+const ANALYSIS_WINDOW_MS: u64 = 20;
+
+/// 分段选项
+#[derive(Clone, Debug)]
+pub struct SplitOptions {
+    /// 每个分段的最大时长（毫秒）
+    pub max_segment_ms: u64,
+    /// 在分段末尾向前查找静音的范围（毫秒），为 0 时严格按最大时长切分
+    pub silence_search_ms: u64,
+}
+
+impl Default for SplitOptions {
+    fn default() -> Self {
+        Self {
+            max_segment_ms: 60 * 60 * 1000,
+            silence_search_ms: 30 * 1000,
+        }
+    }
+}
+
+/// 音频分段
+#[derive(Clone, Debug)]
+pub struct AudioSegment {
+    /// 分段文件路径
+    pub path: PathBuf,
+    /// 分段在原音频中的起始时间（毫秒）
+    pub offset_ms: u64,
+    /// 分段时长（毫秒）
+    pub duration_ms: u64,
+}
+
+/// 将未压缩 WAV 文件切分为多个分段，分段文件写入 `output_dir`
+///
+/// 音频不超过最大时长时也会生成一个分段
+pub async fn split_wav(
+    path: &Path,
+    output_dir: &Path,
+    options: &SplitOptions,
+) -> Result<Vec<AudioSegment>, IflyrecError> {
+    if options.max_segment_ms == 0 {
+        return Err(IflyrecError::InvalidParameter(
+            "max_segment_ms must be greater than 0".to_string(),
+        ));
+    }
+
+    let mut file = File::open(path).await?;
+    let file_size = file.metadata().await?.len();
+    let layout = read_layout(&mut file, file_size).await?;
+    let format = layout.format;
+    if !format.is_uncompressed() || format.block_align == 0 || format.sample_rate == 0 {
+        return Err(IflyrecError::InvalidParameter(
+            "only uncompressed PCM WAV files can be split".to_string(),
+        ));
+    }
+
+    let sample_rate = u64::from(format.sample_rate);
+    let block_align = u64::from(format.block_align);
+    let total_frames = layout.data_size / block_align;
+    let max_frames = (options.max_segment_ms * sample_rate / 1000).max(1);
+    let search_frames = (options.silence_search_ms * sample_rate / 1000).min(max_frames - 1);
+
+    // 计算切分点（以采样帧计）
+    let mut boundaries = vec![0];
+    let mut start = 0;
+    while total_frames - start > max_frames {
+        let window_end = start + max_frames;
+        let cut = quietest_frame(&mut file, &layout, window_end - search_frames, window_end)
+            .await?
+            .max(start + 1);
+        boundaries.push(cut);
+        start = cut;
+    }
+    boundaries.push(total_frames);
+
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("audio")
+        .to_string();
+    let mut segments = Vec::with_capacity(boundaries.len() - 1);
+    for (i, range) in boundaries.windows(2).enumerate() {
+        let (start_frame, end_frame) = (range[0], range[1]);
+        let segment_path = output_dir.join(format!("{stem}_part{:03}.wav", i + 1));
+        let data_size = (end_frame - start_frame) * block_align;
+
+        // 保留原文件的格式标签，浮点音频的分段仍为浮点格式
+        let header = wav_header(
+            &format,
+            u32::try_from(data_size).map_err(|_| {
+                IflyrecError::InvalidParameter("segment exceeds the 4 GiB WAV limit".to_string())
+            })?,
+        )?;
+
+        let mut writer = BufWriter::new(File::create(&segment_path).await?);
+        writer.write_all(&header).await?;
+        file.seek(SeekFrom::Start(
+            layout.data_offset + start_frame * block_align,
+        ))
+        .await?;
+        io::copy(&mut (&mut file).take(data_size), &mut writer).await?;
+        writer.flush().await?;
+
+        segments.push(AudioSegment {
+            path: segment_path,
+            offset_ms: start_frame * 1000 / sample_rate,
+            duration_ms: (end_frame - start_frame) * 1000 / sample_rate,
+        });
+    }
+
+    Ok(segments)
+}
+
+/// 在 `[from, to)` 采样帧范围内查找能量最低的分析窗口，返回其中心位置
+///
+/// 不支持的采样格式直接返回 `to`
+async fn quietest_frame(
+    file: &mut File,
+    layout: &WavLayout,
+    from: u64,
+    to: u64,
+) -> Result<u64, IflyrecError> {
+    let format = layout.format;
+    let block_align = usize::from(format.block_align);
+    let sample_bytes = usize::from(format.bits_per_sample.div_ceil(8));
+    let window_frames = (ANALYSIS_WINDOW_MS * u64::from(format.sample_rate) / 1000).max(1);
+    if to - from < window_frames || sample_bytes == 0 {
+        return Ok(to);
+    }
+
+    let mut buffer = vec![0u8; ((to - from) as usize) * block_align];
+    file.seek(SeekFrom::Start(
+        layout.data_offset + from * u64::from(format.block_align),
+    ))
+    .await?;
+    file.read_exact(&mut buffer).await?;
+
+    let mut quietest = (f64::MAX, to);
+    for (i, window) in buffer
+        .chunks_exact(window_frames as usize * block_align)
+        .enumerate()
+    {
+        let mut energy = 0.0;
+        for sample in window.chunks_exact(sample_bytes) {
+            let Some(value) = decode_sample(sample, format.is_pcm()) else {
+                return Ok(to);
+            };
+            energy += value * value;
+        }
+        if energy < quietest.0 {
+            quietest = (energy, from + i as u64 * window_frames + window_frames / 2);
+        }
+    }
+
+    Ok(quietest.1)
+}
+
+/// 将一个采样归一化到 [-1, 1]
+fn decode_sample(bytes: &[u8], is_pcm: bool) -> Option<f64> {
+    let value = match (bytes.len(), is_pcm) {
+        (1, true) => (f64::from(bytes[0]) - 128.0) / 128.0,
+        (2, true) => f64::from(i16::from_le_bytes([bytes[0], bytes[1]])) / 32_768.0,
+        (3, true) => {
+            f64::from(i32::from_le_bytes([0, bytes[0], bytes[1], bytes[2]]) >> 8) / 8_388_608.0
+        }
+        (4, true) => f64::from(i32::from_le_bytes(bytes.try_into().ok()?)) / 2_147_483_648.0,
+        (4, false) => f64::from(f32::from_le_bytes(bytes.try_into().ok()?)),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::wav::{WAVE_FORMAT_IEEE_FLOAT, WAVE_FORMAT_PCM, WavFormat};
+    use crate::util::temporary_path;
+
+    /// 采样率，取 1 kHz 使每毫秒恰好一帧
+    const SAMPLE_RATE: u32 = 1_000;
+
+    fn mono(format_tag: u16, bits_per_sample: u16) -> WavFormat {
+        WavFormat {
+            format_tag,
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            byte_rate: 0,
+            block_align: bits_per_sample / 8,
+            bits_per_sample,
+        }
+    }
+
+    /// 写入 WAV 文件并切分，返回分段及其目录
+    async fn split(
+        format: &WavFormat,
+        data: &[u8],
+        options: &SplitOptions,
+    ) -> (Vec<AudioSegment>, PathBuf) {
+        let dir = temporary_path("");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("input.wav");
+        let mut file = wav_header(format, data.len() as u32).unwrap();
+        file.extend_from_slice(data);
+        tokio::fs::write(&path, file).await.unwrap();
+
+        let segments = split_wav(&path, &dir, options).await.unwrap();
+        (segments, dir)
+    }
+
+    /// 16 位 PCM 采样，`quiet` 范围内为静音，其余为固定幅度
+    fn pcm16(frames: u64, quiet: std::ops::Range<u64>) -> Vec<u8> {
+        (0..frames)
+            .flat_map(|frame| {
+                let value: i16 = if quiet.contains(&frame) { 0 } else { 10_000 };
+                value.to_le_bytes()
+            })
+            .collect()
+    }
+
+    async fn segment_layout(segment: &AudioSegment) -> WavLayout {
+        let mut file = File::open(&segment.path).await.unwrap();
+        let file_size = file.metadata().await.unwrap().len();
+        read_layout(&mut file, file_size).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn cuts_at_the_quietest_window() {
+        let options = SplitOptions {
+            max_segment_ms: 3_000,
+            silence_search_ms: 1_000,
+        };
+        let (segments, dir) = split(
+            &mono(WAVE_FORMAT_PCM, 16),
+            &pcm16(10_000, 2_500..2_540),
+            &options,
+        )
+        .await;
+
+        let first_cut = segments[1].offset_ms;
+        assert!((2_500..2_540).contains(&first_cut), "cut at {first_cut}");
+        assert_eq!(segments[0].duration_ms, first_cut);
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn segments_cover_the_audio_contiguously() {
+        let options = SplitOptions {
+            max_segment_ms: 3_000,
+            silence_search_ms: 1_000,
+        };
+        let (segments, dir) = split(
+            &mono(WAVE_FORMAT_PCM, 16),
+            &pcm16(10_000, 2_500..2_540),
+            &options,
+        )
+        .await;
+
+        assert!(segments.len() >= 4);
+        let mut expected_offset = 0;
+        for segment in &segments {
+            assert_eq!(segment.offset_ms, expected_offset);
+            assert!(segment.duration_ms <= options.max_segment_ms);
+            assert_eq!(
+                segment_layout(segment).await.data_size,
+                segment.duration_ms * 2
+            );
+            expected_offset += segment.duration_ms;
+        }
+        assert_eq!(expected_offset, 10_000);
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn short_audio_is_a_single_segment() {
+        let (segments, dir) = split(
+            &mono(WAVE_FORMAT_PCM, 16),
+            &pcm16(1_500, 0..0),
+            &SplitOptions::default(),
+        )
+        .await;
+
+        assert_eq!(segments.len(), 1);
+        assert_eq!((segments[0].offset_ms, segments[0].duration_ms), (0, 1_500));
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn float_segments_keep_the_format_tag() {
+        let data: Vec<u8> = (0..4_000).flat_map(|_| 0.25f32.to_le_bytes()).collect();
+        let options = SplitOptions {
+            max_segment_ms: 1_000,
+            silence_search_ms: 0,
+        };
+        let (segments, dir) = split(&mono(WAVE_FORMAT_IEEE_FLOAT, 32), &data, &options).await;
+
+        assert_eq!(segments.len(), 4);
+        for segment in &segments {
+            let layout = segment_layout(segment).await;
+            assert_eq!(layout.format.format_tag, WAVE_FORMAT_IEEE_FLOAT);
+            assert_eq!(layout.format.block_align, 4);
+            assert_eq!(segment.duration_ms, 1_000);
+        }
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rejects_compressed_formats() {
+        let dir = temporary_path("");
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("input.wav");
+        let mut file = wav_header(&mono(0x0011, 16), 100).unwrap();
+        file.extend_from_slice(&[0; 100]);
+        tokio::fs::write(&path, file).await.unwrap();
+
+        assert!(matches!(
+            split_wav(&path, &dir, &SplitOptions::default()).await,
+            Err(IflyrecError::InvalidParameter(_))
+        ));
+
+        tokio::fs::remove_dir_all(dir).await.unwrap();
+    }
+}
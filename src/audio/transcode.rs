@@ -0,0 +1,413 @@
+//! 音频转码
+//!
+//! 将任意常见格式的音频解码、混合为单声道并重采样到 16 kHz，编码为 16 位 PCM WAV，
+//! 即录音笔渠道期望的音频格式。
+
+use super::wav::pcm_header;
+use crate::error::IflyrecError;
+
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, ErrorKind, Seek, SeekFrom, Write};
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CODEC_TYPE_NULL, CODEC_TYPE_PCM_S16LE, DecoderOptions};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// 目标采样率
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
+
+/// 重采样滤波器在截止频率处的单侧过零点数
+const SINC_ZERO_CROSSINGS: f64 = 8.0;
+
+/// 判断音频是否需要转码
+///
+/// 已经是 16 kHz 单声道 16 位 PCM WAV 的文件无需转码
+pub async fn needs_normalization(path: &Path) -> Result<bool, IflyrecError> {
+    let path = path.to_path_buf();
+    run_blocking(move || {
+        let format = open_format(&path)?;
+        let is_wav = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("wav"));
+        let params = &format
+            .default_track()
+            .ok_or_else(|| transcode_error("no audio track found"))?
+            .codec_params;
+
+        let normalized = is_wav
+            && params.codec == CODEC_TYPE_PCM_S16LE
+            && params.sample_rate == Some(TARGET_SAMPLE_RATE)
+            && params.channels.map(|channels| channels.count()) == Some(1);
+        Ok(!normalized)
+    })
+    .await
+}
+
+/// 将音频转码为 16 kHz 单声道 16 位 PCM WAV
+///
+/// 解码、混音和重采样均以数据包为单位流式进行，内存占用与音频长度无关
+pub async fn transcode_to_wav(input: &Path, output: &Path) -> Result<(), IflyrecError> {
+    let input = input.to_path_buf();
+    let output = output.to_path_buf();
+    run_blocking(move || transcode_blocking(&input, &output)).await
+}
+
+async fn run_blocking<T, F>(f: F) -> Result<T, IflyrecError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, IflyrecError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| transcode_error(&e.to_string()))?
+}
+
+fn open_format(path: &Path) -> Result<Box<dyn FormatReader>, IflyrecError> {
+    let file = File::open(path)?;
+    let source = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(map_symphonia_error)?;
+    Ok(probed.format)
+}
+
+fn transcode_blocking(input: &Path, output: &Path) -> Result<(), IflyrecError> {
+    let mut format = open_format(input)?;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| transcode_error("no audio track found"))?;
+    let track_id = track.id;
+    let source_rate = track
+        .codec_params
+        .sample_rate
+        .ok_or_else(|| transcode_error("unknown sample rate"))?;
+
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(map_symphonia_error)?;
+
+    let mut resampler = Resampler::new(source_rate, TARGET_SAMPLE_RATE);
+    let mut writer = WavWriter::create(output, TARGET_SAMPLE_RATE)?;
+    let mut sample_buffer: Option<SampleBuffer<f32>> = None;
+    let mut mono = Vec::new();
+    let mut resampled = Vec::new();
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(map_symphonia_error(e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // 跳过损坏的数据包
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::warn!("跳过无法解码的数据包：{e}");
+                continue;
+            }
+            Err(e) => return Err(map_symphonia_error(e)),
+        };
+
+        let spec = *decoded.spec();
+        let buffer =
+            sample_buffer.get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, spec));
+        if buffer.capacity() < decoded.capacity() * spec.channels.count() {
+            *buffer = SampleBuffer::new(decoded.capacity() as u64, spec);
+        }
+        buffer.copy_interleaved_ref(decoded);
+
+        // 各声道取平均混合为单声道
+        let channels = spec.channels.count().max(1);
+        mono.clear();
+        mono.extend(
+            buffer
+                .samples()
+                .chunks_exact(channels)
+                .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+        );
+
+        resampled.clear();
+        resampler.process(&mono, &mut resampled);
+        writer.write_samples(&resampled)?;
+    }
+
+    resampled.clear();
+    resampler.finish(&mut resampled);
+    writer.write_samples(&resampled)?;
+    writer.finish()
+}
+
+/// 流式带限重采样器（加汉宁窗的 sinc 插值）
+struct Resampler {
+    /// 输入采样率与输出采样率之比
+    step: f64,
+    /// 归一化截止频率（相对输入奈奎斯特频率）
+    cutoff: f64,
+    /// 滤波器单侧宽度（输入采样数）
+    half_width: usize,
+    /// 尚需保留的输入采样
+    buffer: Vec<f32>,
+    /// `buffer[0]` 对应的输入采样序号
+    buffer_start: u64,
+    /// 下一个输出采样的序号
+    next_output: u64,
+    /// 已接收的输入采样总数
+    input_len: u64,
+}
+
+impl Resampler {
+    fn new(source_rate: u32, target_rate: u32) -> Self {
+        let step = f64::from(source_rate) / f64::from(target_rate);
+        // 降采样时将截止频率降到目标奈奎斯特频率以下，避免混叠
+        let cutoff = (1.0 / step).min(1.0) * 0.95;
+        Self {
+            step,
+            cutoff,
+            half_width: (SINC_ZERO_CROSSINGS / cutoff).ceil() as usize,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            next_output: 0,
+            input_len: 0,
+        }
+    }
+
+    /// 输入一段采样，将已可计算的输出采样追加到 `output`
+    fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.buffer.extend_from_slice(input);
+        self.input_len += input.len() as u64;
+        self.drain(output, false);
+    }
+
+    /// 输入结束，输出剩余采样
+    fn finish(&mut self, output: &mut Vec<f32>) {
+        self.drain(output, true);
+    }
+
+    fn drain(&mut self, output: &mut Vec<f32>, flush: bool) {
+        let half = self.half_width as i64;
+        let expected_outputs = (self.input_len as f64 / self.step).ceil() as u64;
+
+        loop {
+            let position = self.next_output as f64 * self.step;
+            let center = position.floor() as i64;
+            if flush {
+                if self.next_output >= expected_outputs {
+                    break;
+                }
+            } else if center + half >= self.input_len as i64 {
+                break;
+            }
+
+            output.push(self.interpolate(position, center, half));
+            self.next_output += 1;
+        }
+
+        // 丢弃之后不再需要的输入采样
+        let next_center = (self.next_output as f64 * self.step).floor() as i64;
+        let keep_from = (next_center - half).max(self.buffer_start as i64) as u64;
+        let discard = (keep_from - self.buffer_start) as usize;
+        self.buffer.drain(..discard.min(self.buffer.len()));
+        self.buffer_start += discard as u64;
+    }
+
+    fn interpolate(&self, position: f64, center: i64, half: i64) -> f32 {
+        let mut sum = 0.0;
+        for index in (center - half + 1)..=(center + half) {
+            let offset = index - self.buffer_start as i64;
+            if offset < 0 || offset >= self.buffer.len() as i64 {
+                continue;
+            }
+            let distance = position - index as f64;
+            sum += f64::from(self.buffer[offset as usize]) * self.kernel(distance);
+        }
+        sum as f32
+    }
+
+    fn kernel(&self, distance: f64) -> f64 {
+        let x = distance * self.cutoff;
+        let sinc = if x.abs() < 1e-9 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        };
+        let window = 0.5 * (1.0 + (PI * distance / self.half_width as f64).cos());
+        self.cutoff * sinc * window
+    }
+}
+
+/// 16 位单声道 PCM WAV 写入器
+struct WavWriter {
+    writer: BufWriter<File>,
+    data_bytes: u64,
+}
+
+impl WavWriter {
+    /// 创建文件并写入占位头部，数据长度在 [`WavWriter::finish`] 时回填
+    fn create(path: &Path, sample_rate: u32) -> Result<Self, IflyrecError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&pcm_header(1, sample_rate, 16, 0)?)?;
+
+        Ok(Self {
+            writer,
+            data_bytes: 0,
+        })
+    }
+
+    fn write_samples(&mut self, samples: &[f32]) -> Result<(), IflyrecError> {
+        for &sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16;
+            self.writer.write_all(&value.to_le_bytes())?;
+        }
+        self.data_bytes += samples.len() as u64 * 2;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<(), IflyrecError> {
+        let riff_size = u32::try_from(36 + self.data_bytes)
+            .map_err(|_| transcode_error("output exceeds the 4 GiB WAV limit"))?;
+
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer.write_all(&riff_size.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(40))?;
+        self.writer.write_all(&(riff_size - 36).to_le_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+fn map_symphonia_error(error: SymphoniaError) -> IflyrecError {
+    match error {
+        SymphoniaError::IoError(e) => IflyrecError::IoError(e),
+        e => transcode_error(&e.to_string()),
+    }
+}
+
+fn transcode_error(reason: &str) -> IflyrecError {
+    IflyrecError::TranscodeError(reason.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::temporary_path;
+
+    /// 分块输入重采样器，模拟逐个数据包解码
+    fn resample(source_rate: u32, input: &[f32]) -> Vec<f32> {
+        let mut resampler = Resampler::new(source_rate, TARGET_SAMPLE_RATE);
+        let mut output = Vec::new();
+        for chunk in input.chunks(1_000) {
+            resampler.process(chunk, &mut output);
+        }
+        resampler.finish(&mut output);
+        output
+    }
+
+    fn sine(rate: u32, frequency: f64, amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * PI * frequency * i as f64 / f64::from(rate)).sin() as f32)
+            .collect()
+    }
+
+    fn read_i16(bytes: &[u8]) -> Vec<i16> {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect()
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn resamples_to_target_rate() {
+        for source_rate in [44_100, 48_000] {
+            let output = resample(source_rate, &vec![0.0; source_rate as usize]);
+            assert_eq!(output.len(), TARGET_SAMPLE_RATE as usize, "{source_rate}");
+        }
+    }
+
+    #[test]
+    fn keeps_dc_amplitude() {
+        let output = resample(44_100, &vec![0.5; 44_100]);
+        // 开头和结尾的滤波器未填满，只检查中间部分
+        for &sample in &output[100..output.len() - 100] {
+            assert!((sample - 0.5).abs() < 0.01, "{sample}");
+        }
+    }
+
+    #[test]
+    fn keeps_low_frequency_amplitude() {
+        let output = resample(48_000, &sine(48_000, 200.0, 0.8, 48_000));
+        let peak = output[100..output.len() - 100]
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.8).abs() < 0.02, "{peak}");
+    }
+
+    #[test]
+    fn patches_sizes_after_finish() {
+        let path = temporary_path(".wav");
+        let mut writer = WavWriter::create(&path, TARGET_SAMPLE_RATE).unwrap();
+        writer.write_samples(&[0.0, 0.5, -0.5]).unwrap();
+        writer.write_samples(&[1.0]).unwrap();
+        writer.finish().unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes.len(), 44 + 8);
+        assert_eq!(read_u32(&bytes, 4), 36 + 8);
+        assert_eq!(read_u32(&bytes, 40), 8);
+        assert_eq!(read_i16(&bytes[44..]), [0, 16_384, -16_384, i16::MAX]);
+    }
+
+    #[test]
+    fn downmixes_stereo_to_mono() {
+        let frames = TARGET_SAMPLE_RATE as usize;
+        let mut input = pcm_header(2, TARGET_SAMPLE_RATE, 16, frames as u32 * 4).unwrap();
+        for _ in 0..frames {
+            input.extend_from_slice(&16_000i16.to_le_bytes());
+            input.extend_from_slice(&8_000i16.to_le_bytes());
+        }
+        let input_path = temporary_path(".wav");
+        let output_path = temporary_path(".wav");
+        std::fs::write(&input_path, input).unwrap();
+
+        transcode_blocking(&input_path, &output_path).unwrap();
+        let output = std::fs::read(&output_path).unwrap();
+        std::fs::remove_file(&input_path).unwrap();
+        std::fs::remove_file(&output_path).unwrap();
+
+        // 单声道头部：声道数和块对齐
+        assert_eq!(u16::from_le_bytes([output[22], output[23]]), 1);
+        assert_eq!(u16::from_le_bytes([output[32], output[33]]), 2);
+        let samples = read_i16(&output[44..]);
+        assert_eq!(samples.len(), frames);
+        for &sample in &samples[100..frames - 100] {
+            assert!((i32::from(sample) - 12_000).abs() < 120, "{sample}");
+        }
+    }
+}
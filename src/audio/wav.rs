@@ -0,0 +1,436 @@
+//! WAV 头部解析
+
+use super::malformed;
+use crate::error::IflyrecError;
+
+use std::io::SeekFrom;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt};
+
+/// PCM 格式标签
+pub(crate) const WAVE_FORMAT_PCM: u16 = 0x0001;
+/// IEEE 浮点格式标签
+pub(crate) const WAVE_FORMAT_IEEE_FLOAT: u16 = 0x0003;
+/// 扩展格式标签，实际格式由子格式 GUID 的前两个字节决定
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// WAV 文件 `fmt ` 块中的字段
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WavFormat {
+    pub format_tag: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub byte_rate: u32,
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+}
+
+impl WavFormat {
+    /// 是否为未压缩格式
+    pub fn is_uncompressed(&self) -> bool {
+        matches!(self.format_tag, WAVE_FORMAT_PCM | WAVE_FORMAT_IEEE_FLOAT)
+    }
+
+    /// 是否为整数 PCM 格式
+    pub fn is_pcm(&self) -> bool {
+        self.format_tag == WAVE_FORMAT_PCM
+    }
+}
+
+/// WAV 文件结构
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct WavLayout {
+    pub format: WavFormat,
+    /// `data` 块内容的起始偏移
+    pub data_offset: u64,
+    /// `data` 块内容的长度
+    pub data_size: u64,
+    /// `fact` 块中的采样帧数
+    pub sample_frames: Option<u32>,
+}
+
+/// 解析 RIFF/WAVE 头部，计算时长（毫秒）
+pub async fn duration_ms<R>(reader: &mut R, file_size: u64) -> Result<u64, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let WavLayout {
+        format,
+        data_size,
+        sample_frames,
+        ..
+    } = read_layout(reader, file_size).await?;
+
+    // 压缩格式优先使用 fact 块中的采样帧数
+    if !format.is_uncompressed()
+        && let Some(frames) = sample_frames
+        && format.sample_rate > 0
+    {
+        return Ok(u64::from(frames) * 1000 / u64::from(format.sample_rate));
+    }
+
+    let byte_rate = match format.byte_rate {
+        0 => u64::from(format.sample_rate) * u64::from(format.block_align),
+        byte_rate => u64::from(byte_rate),
+    };
+    if byte_rate == 0 {
+        return Err(malformed("WAV", "byte rate is zero"));
+    }
+
+    Ok(data_size * 1000 / byte_rate)
+}
+
+/// 解析 RIFF/WAVE 头部
+///
+/// 支持 WAVE_FORMAT_EXTENSIBLE、`LIST`/`JUNK` 等附加块以及奇数长度块的填充字节。
+/// `data` 块长度无效（如流式写入时未回填）时，以文件剩余长度代替。
+pub(crate) async fn read_layout<R>(
+    reader: &mut R,
+    file_size: u64,
+) -> Result<WavLayout, IflyrecError>
+where
+    R: AsyncRead + AsyncSeek + Unpin,
+{
+    let mut riff_header = [0u8; 12];
+    reader
+        .read_exact(&mut riff_header)
+        .await
+        .map_err(|_| malformed("WAV", "file too short for RIFF header"))?;
+    if &riff_header[0..4] != b"RIFF" || &riff_header[8..12] != b"WAVE" {
+        return Err(malformed("WAV", "missing RIFF/WAVE signature"));
+    }
+
+    let mut format: Option<WavFormat> = None;
+    let mut data: Option<(u64, u64)> = None;
+    let mut sample_frames: Option<u32> = None;
+    let mut offset: u64 = 12;
+
+    while offset + 8 <= file_size {
+        let mut chunk_header = [0u8; 8];
+        reader
+            .read_exact(&mut chunk_header)
+            .await
+            .map_err(truncated)?;
+        let chunk_id = &chunk_header[0..4];
+        let chunk_size = u64::from(u32::from_le_bytes(chunk_header[4..8].try_into().unwrap()));
+        let body_offset = offset + 8;
+
+        match chunk_id {
+            b"fmt " => format = Some(read_fmt_chunk(reader, chunk_size).await?),
+            b"fact" if chunk_size >= 4 => {
+                sample_frames = Some(reader.read_u32_le().await.map_err(truncated)?);
+            }
+            b"data" => {
+                let remaining = file_size.saturating_sub(body_offset);
+                let data_size = if chunk_size == u64::from(u32::MAX) {
+                    remaining
+                } else {
+                    chunk_size.min(remaining)
+                };
+                data = Some((body_offset, data_size));
+            }
+            _ => {}
+        }
+
+        if format.is_some() && data.is_some() {
+            break;
+        }
+
+        // 块长度为奇数时后跟一个填充字节
+        offset = body_offset + chunk_size + (chunk_size & 1);
+        reader.seek(SeekFrom::Start(offset)).await?;
+    }
+
+    let format = format.ok_or_else(|| malformed("WAV", "missing fmt chunk"))?;
+    let (data_offset, data_size) = data.ok_or_else(|| malformed("WAV", "missing data chunk"))?;
+
+    Ok(WavLayout {
+        format,
+        data_offset,
+        data_size,
+        sample_frames,
+    })
+}
+
+/// 读取 `fmt ` 块
+async fn read_fmt_chunk<R>(reader: &mut R, chunk_size: u64) -> Result<WavFormat, IflyrecError>
+where
+    R: AsyncRead + Unpin,
+{
+    if chunk_size < 16 {
+        return Err(malformed("WAV", "fmt chunk too short"));
+    }
+
+    let mut fields = [0u8; 16];
+    reader.read_exact(&mut fields).await.map_err(truncated)?;
+    let u16_at = |i: usize| u16::from_le_bytes([fields[i], fields[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes(fields[i..i + 4].try_into().unwrap());
+
+    let mut format_tag = u16_at(0);
+    let channels = u16_at(2);
+    let sample_rate = u32_at(4);
+    let byte_rate = u32_at(8);
+    let block_align = u16_at(12);
+    let bits_per_sample = u16_at(14);
+
+    if format_tag == WAVE_FORMAT_EXTENSIBLE {
+        // cbSize(2) + wValidBitsPerSample(2) + dwChannelMask(4) + SubFormat GUID(16)
+        if chunk_size < 40 {
+            return Err(malformed(
+                "WAV",
+                "WAVE_FORMAT_EXTENSIBLE fmt chunk too short",
+            ));
+        }
+        let mut extension = [0u8; 10];
+        reader.read_exact(&mut extension).await.map_err(truncated)?;
+        format_tag = u16::from_le_bytes([extension[8], extension[9]]);
+    }
+
+    Ok(WavFormat {
+        format_tag,
+        channels,
+        sample_rate,
+        byte_rate,
+        block_align,
+        bits_per_sample,
+    })
+}
+
+/// 读取头部时遇到文件结尾说明头部被截断
+fn truncated(error: std::io::Error) -> IflyrecError {
+    if error.kind() == std::io::ErrorKind::UnexpectedEof {
+        malformed("WAV", "truncated header")
+    } else {
+        IflyrecError::IoError(error)
+    }
+}
+
+/// 生成 44 字节的 PCM WAV 头部
+#[cfg(feature = "transcode")]
+pub(crate) fn pcm_header(
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    data_size: u32,
+) -> Result<Vec<u8>, IflyrecError> {
+    let block_align = channels
+        .checked_mul(bits_per_sample.div_ceil(8))
+        .ok_or_else(|| malformed("WAV", "block align overflows"))?;
+    let format = WavFormat {
+        format_tag: WAVE_FORMAT_PCM,
+        channels,
+        sample_rate,
+        byte_rate: 0,
+        block_align,
+        bits_per_sample,
+    };
+    wav_header(&format, data_size)
+}
+
+/// 生成 44 字节的未压缩 WAV 头部，`format.format_tag` 为 PCM 或 IEEE 浮点
+///
+/// 沿用 `format.block_align`，字节率由采样率和 `block_align` 重新计算
+pub(crate) fn wav_header(format: &WavFormat, data_size: u32) -> Result<Vec<u8>, IflyrecError> {
+    let byte_rate = format
+        .sample_rate
+        .checked_mul(u32::from(format.block_align))
+        .ok_or_else(|| malformed("WAV", "byte rate overflows"))?;
+
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&data_size.saturating_add(36).to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&format.format_tag.to_le_bytes());
+    header.extend_from_slice(&format.channels.to_le_bytes());
+    header.extend_from_slice(&format.sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&format.block_align.to_le_bytes());
+    header.extend_from_slice(&format.bits_per_sample.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_size.to_le_bytes());
+    Ok(header)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// 构造一个块
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    /// 构造 16 字节的 `fmt ` 块内容
+    fn fmt_body(format_tag: u16, channels: u16, sample_rate: u32, bits: u16) -> Vec<u8> {
+        let block_align = channels * bits / 8;
+        let mut body = Vec::new();
+        body.extend_from_slice(&format_tag.to_le_bytes());
+        body.extend_from_slice(&channels.to_le_bytes());
+        body.extend_from_slice(&sample_rate.to_le_bytes());
+        body.extend_from_slice(&(sample_rate * u32::from(block_align)).to_le_bytes());
+        body.extend_from_slice(&block_align.to_le_bytes());
+        body.extend_from_slice(&bits.to_le_bytes());
+        body
+    }
+
+    /// 由若干块构造 RIFF/WAVE 文件
+    fn riff(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let body: Vec<u8> = chunks.concat();
+        let mut file = b"RIFF".to_vec();
+        file.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+        file.extend_from_slice(b"WAVE");
+        file.extend_from_slice(&body);
+        file
+    }
+
+    async fn layout(bytes: &[u8]) -> Result<WavLayout, IflyrecError> {
+        read_layout(&mut Cursor::new(bytes), bytes.len() as u64).await
+    }
+
+    async fn duration(bytes: &[u8]) -> Result<u64, IflyrecError> {
+        duration_ms(&mut Cursor::new(bytes), bytes.len() as u64).await
+    }
+
+    #[tokio::test]
+    async fn parses_pcm_duration() {
+        // 16 kHz 单声道 16 位，1.5 秒
+        let file = riff(&[
+            chunk(b"fmt ", &fmt_body(WAVE_FORMAT_PCM, 1, 16_000, 16)),
+            chunk(b"data", &vec![0; 48_000]),
+        ]);
+        assert_eq!(duration(&file).await.unwrap(), 1500);
+
+        let layout = layout(&file).await.unwrap();
+        assert!(layout.format.is_pcm());
+        assert_eq!(layout.data_offset, 44);
+        assert_eq!(layout.data_size, 48_000);
+    }
+
+    #[tokio::test]
+    async fn resolves_extensible_subformat() {
+        let mut body = fmt_body(WAVE_FORMAT_EXTENSIBLE, 2, 48_000, 32);
+        body.extend_from_slice(&22u16.to_le_bytes());
+        body.extend_from_slice(&32u16.to_le_bytes());
+        body.extend_from_slice(&3u32.to_le_bytes());
+        // KSDATAFORMAT_SUBTYPE_IEEE_FLOAT
+        body.extend_from_slice(&[
+            0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xAA, 0x00, 0x38,
+            0x9B, 0x71,
+        ]);
+        let file = riff(&[chunk(b"fmt ", &body), chunk(b"data", &vec![0; 384_000])]);
+
+        let layout = layout(&file).await.unwrap();
+        assert_eq!(layout.format.format_tag, WAVE_FORMAT_IEEE_FLOAT);
+        assert!(layout.format.is_uncompressed());
+        assert!(!layout.format.is_pcm());
+        assert_eq!(duration(&file).await.unwrap(), 1000);
+    }
+
+    #[tokio::test]
+    async fn rejects_short_extensible_fmt_chunk() {
+        let file = riff(&[
+            chunk(b"fmt ", &fmt_body(WAVE_FORMAT_EXTENSIBLE, 1, 8_000, 16)),
+            chunk(b"data", &[0; 16]),
+        ]);
+        assert!(matches!(
+            layout(&file).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn skips_odd_length_chunks_with_padding() {
+        let file = riff(&[
+            chunk(b"LIST", b"INFOabc"),
+            chunk(b"fmt ", &fmt_body(WAVE_FORMAT_PCM, 1, 8_000, 8)),
+            chunk(b"JUNK", &[0; 3]),
+            chunk(b"data", &vec![0; 4_000]),
+        ]);
+        let layout = layout(&file).await.unwrap();
+        assert_eq!(layout.data_offset, 12 + 16 + 24 + 12 + 8);
+        assert_eq!(layout.data_size, 4_000);
+    }
+
+    #[tokio::test]
+    async fn uses_fact_frames_for_compressed_formats() {
+        // IMA ADPCM，字节率与时长无直接关系
+        let file = riff(&[
+            chunk(b"fmt ", &fmt_body(0x0011, 1, 8_000, 4)),
+            chunk(b"fact", &16_000u32.to_le_bytes()),
+            chunk(b"data", &[0; 100]),
+        ]);
+        assert_eq!(duration(&file).await.unwrap(), 2000);
+    }
+
+    #[tokio::test]
+    async fn unset_data_size_uses_remaining_bytes() {
+        let mut file = riff(&[
+            chunk(b"fmt ", &fmt_body(WAVE_FORMAT_PCM, 1, 8_000, 16)),
+            chunk(b"data", &vec![0; 8_000]),
+        ]);
+        file[40..44].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(layout(&file).await.unwrap().data_size, 8_000);
+    }
+
+    #[tokio::test]
+    async fn truncated_header_is_malformed() {
+        let file = riff(&[chunk(b"fmt ", &fmt_body(WAVE_FORMAT_PCM, 1, 8_000, 16))]);
+        for len in [4, 18, 30] {
+            assert!(
+                matches!(
+                    layout(&file[..len]).await,
+                    Err(IflyrecError::DurationCalculationError(_))
+                ),
+                "length {len}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn rejects_missing_signature_and_chunks() {
+        assert!(layout(b"RIFX\0\0\0\0WAVE").await.is_err());
+        let no_data = riff(&[chunk(b"fmt ", &fmt_body(WAVE_FORMAT_PCM, 1, 8_000, 16))]);
+        assert!(matches!(
+            layout(&no_data).await,
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+
+    fn float_stereo(sample_rate: u32, block_align: u16) -> WavFormat {
+        WavFormat {
+            format_tag: WAVE_FORMAT_IEEE_FLOAT,
+            channels: 2,
+            sample_rate,
+            byte_rate: 0,
+            block_align,
+            bits_per_sample: 32,
+        }
+    }
+
+    #[tokio::test]
+    async fn wav_header_round_trips() {
+        let mut file = wav_header(&float_stereo(44_100, 8), 352_800).unwrap();
+        file.resize(44 + 352_800, 0);
+        let layout = layout(&file).await.unwrap();
+        assert_eq!(layout.format.format_tag, WAVE_FORMAT_IEEE_FLOAT);
+        assert_eq!(layout.format.block_align, 8);
+        assert_eq!(layout.data_offset, 44);
+        assert_eq!(duration(&file).await.unwrap(), 1000);
+    }
+
+    #[test]
+    fn wav_header_rejects_overflowing_byte_rate() {
+        assert!(matches!(
+            wav_header(&float_stereo(u32::MAX, 8), 0),
+            Err(IflyrecError::DurationCalculationError(_))
+        ));
+    }
+}
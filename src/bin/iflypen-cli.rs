@@ -1,28 +1,188 @@
-use clap::Parser;
-use iflypen_api_rs::{IflyrecClient, TranscriptionOptions, TranscriptionOrder, IflyrecError};
-use rusqlite::{Connection, Result as SqlResult};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use futures_util::{StreamExt, TryStreamExt};
+use iflypen_api_rs::session::{
+    CookieDbSession, DEFAULT_SESSION_ENV, EnvSession, FirefoxSession, SessionChain, SessionCookie,
+    SessionProvider,
+};
+use iflypen_api_rs::{
+    AudioPreprocess, IflyrecClient, IflyrecError, OrderState, Profile, ProfileConfig,
+    ProgressEvent, ProgressSink, TranscriptResult, TranscriptionOptions, TranscriptionOrder,
+    UploadManifest, UploadOptions, WaitOptions,
+};
+use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::HashMap;
+use std::fmt::Write as _;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::Duration;
-use tokio_retry::Retry;
-use tokio_retry::strategy::ExponentialBackoff;
 
-#[derive(Clone, Parser)]
-struct Args {
-    /// 要上传的音频文件路径
+/// 讯飞听见命令行工具
+///
+/// 结果（文件ID、订单ID、订单列表等）输出到标准输出，进度和提示信息输出到标准错误
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(flatten)]
+    session: SessionArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 上传音频文件，输出文件ID
+    Upload(UploadArgs),
+    /// 为已上传的音频提交转录订单，输出订单ID
+    Submit {
+        /// 上传得到的文件ID
+        file_id: String,
+        #[command(flatten)]
+        options: TranscriptionArgs,
+    },
+    /// 上传音频、提交订单、等待完成并保存转写结果
+    Transcribe {
+        #[command(flatten)]
+        upload: UploadArgs,
+        #[command(flatten)]
+        options: TranscriptionArgs,
+        #[command(flatten)]
+        wait: WaitArgs,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// 查询订单状态
+    Status {
+        /// 订单ID
+        order_id: String,
+        /// 等待订单完成
+        #[arg(long = "wait", action = clap::ArgAction::SetTrue)]
+        wait_for_completion: bool,
+        #[command(flatten)]
+        wait: WaitArgs,
+    },
+    /// 列出订单，最近的在前
+    List {
+        /// 最多列出的订单数量，默认只列出第一页
+        #[arg(short = 'n', long = "limit")]
+        limit: Option<usize>,
+        /// 列出所有订单
+        #[arg(short = 'a', long = "all", conflicts_with = "limit", action = clap::ArgAction::SetTrue)]
+        all: bool,
+    },
+    /// 将已完成订单的转写结果保存为文本文件
+    Download {
+        /// 订单ID
+        order_id: String,
+        /// 输出文件路径，默认为「订单名称.txt」
+        #[arg(short = 'o', long = "output")]
+        output: Option<PathBuf>,
+    },
+    /// 以指定格式导出已完成订单的转写结果
+    Export {
+        /// 订单ID
+        order_id: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// 列出候选的会话 Cookie 及将要使用的会话
+    Session {
+        /// 同时向服务器校验会话是否有效
+        #[arg(long = "check", action = clap::ArgAction::SetTrue)]
+        check: bool,
+    },
+}
+
+/// 会话来源参数，可用于所有子命令
+#[derive(Args)]
+#[command(next_help_heading = "会话选项")]
+struct SessionArgs {
+    /// 数据库路径
+    #[arg(
+        short = 'd',
+        long = "db",
+        global = true,
+        default_value = "Cookies",
+        help = "Chromium 或 Firefox 的 Cookie 数据库路径，用于提取 session_id"
+    )]
+    database_path: String,
+
+    /// Cookie 加密口令
+    #[arg(
+        long = "key-password",
+        global = true,
+        help = "解密 Cookie 使用的口令（v11 Cookie 需提供系统密钥环中的口令），默认尝试 peanuts"
+    )]
+    key_password: Option<String>,
+
+    /// 会话 Cookie 名称
+    #[arg(
+        long = "cookie-name",
+        global = true,
+        help = "只使用指定名称的会话 Cookie，默认匹配名称包含 session 的 Cookie"
+    )]
+    cookie_name: Option<String>,
+
+    /// 会话 Cookie 域名
+    #[arg(
+        long = "cookie-host",
+        global = true,
+        help = "只使用指定域名（及其子域名）的会话 Cookie，默认匹配域名包含 iflyrec 的 Cookie"
+    )]
+    cookie_host: Option<String>,
+
+    /// 账号配置名
     #[arg(
-        short = 'f',
-        long = "file",
-        required_unless_present = "order_id",
-        conflicts_with = "order_id",
-        help = "音频文件路径"
+        short = 'p',
+        long = "profile",
+        global = true,
+        help = "使用配置文件中指定账号的会话来源和默认转录选项",
+        conflicts_with_all = ["key_password", "cookie_name", "cookie_host"]
     )]
-    audio_file: Option<String>,
+    profile: Option<String>,
+
+    /// 账号配置文件路径
+    #[arg(
+        long = "profiles",
+        global = true,
+        help = "账号配置文件路径，默认读取 IFLYREC_PROFILES 或 ~/.config/iflypen/profiles.json"
+    )]
+    profiles_path: Option<String>,
+}
+
+/// 上传参数
+#[derive(Args)]
+struct UploadArgs {
+    /// 要上传的音频文件路径
+    #[arg(short = 'f', long = "file", help = "音频文件路径")]
+    audio_file: String,
 
     /// 转录任务名称
     #[arg(short = 'n', long = "name", help = "为此次转录任务指定名称")]
     task_name: Option<String>,
 
+    /// 续传上次中断的上传
+    #[arg(
+        short = 'r',
+        long = "resume",
+        help = "记录上传清单以便中断后续传；若已存在清单，则续传上次中断的上传",
+        action = clap::ArgAction::SetTrue
+    )]
+    resume: bool,
+
+    /// 上传前转码音频
+    #[arg(
+        long = "normalize-audio",
+        help = "上传前将音频转码为 16 kHz 单声道 16 位 PCM WAV（已是该格式时跳过）",
+        action = clap::ArgAction::SetTrue
+    )]
+    normalize_audio: bool,
+}
+
+/// 转录选项参数
+#[derive(Args)]
+struct TranscriptionArgs {
     /// 热词设置（多个词用英文逗号分隔）
     #[arg(
         short = 'w',
@@ -35,10 +195,9 @@ struct Args {
     #[arg(
         short = 'l',
         long = "lang",
-        default_value = "cn",
-        help = "指定音频语言类型（如：cn, en）"
+        help = "指定音频语言类型（如：cn, en），默认使用账号配置中的语言或 cn"
     )]
-    language: String,
+    language: Option<String>,
 
     /// 是否需要短信通知
     #[arg(
@@ -48,50 +207,68 @@ struct Args {
         action = clap::ArgAction::SetTrue
     )]
     need_sms: bool,
+}
 
-    /// 数据库路径
+/// 等待参数
+#[derive(Args)]
+struct WaitArgs {
+    /// 等待转录完成的最长时间（分钟）
     #[arg(
-        short = 'd',
-        long = "db",
-        default_value = "Cookies",
-        help = "SQLite 数据库路径，用于提取 session_id"
+        short = 't',
+        long = "timeout",
+        default_value_t = 360,
+        help = "等待转录完成的最长时间（分钟）"
     )]
-    database_path: String,
+    timeout_minutes: u64,
 
-    /// 通过订单ID下载转写结果
+    /// 查询订单状态的间隔（秒）
     #[arg(
-        short = 'o',
-        long = "order-id",required_unless_present = "audio_file",
-        conflicts_with_all = ["audio_file", "task_name", "hot_words", "language", "need_sms"],
-        help = "指定已有订单ID直接下载结果"
+        long = "poll-interval",
+        default_value_t = 10,
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "查询订单状态的初始间隔（秒）"
     )]
-    order_id: Option<String>,
+    poll_interval: u64,
 }
 
-/// 从数据库获取最频繁使用的session_id
-fn get_most_frequent_session_id(database_path: &str) -> SqlResult<Option<String>> {
-    let conn = Connection::open(database_path)?;
-    let mut stmt = conn.prepare(
-        "SELECT value FROM cookies WHERE name LIKE '%session%' AND host_key LIKE '%iflyrec%'",
-    )?;
+/// 导出参数
+#[derive(Args)]
+struct OutputArgs {
+    /// 导出格式
+    #[arg(long = "format", value_enum, default_value_t = ExportFormat::Txt)]
+    format: ExportFormat,
 
-    let mut value_counts: HashMap<String, usize> = HashMap::new();
-    let rows = stmt.query_map([], |row| {
-        let value: String = row.get(0)?;
-        Ok(value)
-    })?;
-
-    for value_result in rows {
-        let value = value_result?;
-        *value_counts.entry(value).or_insert(0) += 1;
-    }
+    /// 输出文件路径
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "输出文件路径，- 表示标准输出；默认为「订单名称.格式扩展名」"
+    )]
+    output: Option<PathBuf>,
+}
 
-    let most_frequent = value_counts
-        .into_iter()
-        .max_by_key(|(_, count)| *count)
-        .map(|(value, _)| value);
+/// 导出格式
+#[derive(Clone, Copy, ValueEnum)]
+enum ExportFormat {
+    /// 纯文本，段落之间空一行
+    Txt,
+    /// 带说话人和时间的 Markdown
+    Md,
+    /// SRT 字幕
+    Srt,
+    /// 原始转写结果（JSON）
+    Json,
+}
 
-    Ok(most_frequent)
+impl ExportFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Txt => "txt",
+            Self::Md => "md",
+            Self::Srt => "srt",
+            Self::Json => "json",
+        }
+    }
 }
 
 /// 处理热词：将英文逗号替换为中文逗号
@@ -102,111 +279,511 @@ fn process_hot_words(hot_words: Option<String>) -> String {
 }
 
 /// 构建转录选项
-fn build_transcription_options(args: &Args) -> Option<TranscriptionOptions> {
-    let hot_words = process_hot_words(args.hot_words.clone());
+///
+/// 命令行参数优先，未指定的选项使用账号配置中的默认值
+fn build_transcription_options(
+    args: &TranscriptionArgs,
+    defaults: Option<&TranscriptionOptions>,
+) -> Option<TranscriptionOptions> {
+    let defaults = defaults.cloned().unwrap_or_default();
+    let hot_words = match &args.hot_words {
+        Some(_) => process_hot_words(args.hot_words.clone()),
+        None => defaults.hot_words,
+    };
 
     Some(TranscriptionOptions {
-        need_sms: args.need_sms,
+        need_sms: args.need_sms || defaults.need_sms,
         hot_words,
-        language: args.language.clone(),
+        language: args.language.clone().unwrap_or(defaults.language),
     })
 }
 
-#[tokio::main]
-async fn main() -> Result<(), IflyrecError> {
-    let args = Args::parse();
+/// 构建等待选项
+fn build_wait_options(args: &WaitArgs, progress: ProgressSink) -> WaitOptions {
+    WaitOptions {
+        interval: Duration::from_secs(args.poll_interval),
+        timeout: Duration::from_secs(args.timeout_minutes * 60),
+        progress: Some(progress),
+        ..WaitOptions::default()
+    }
+}
 
-    // 从数据库获取 session_id
-    let session_id = match get_most_frequent_session_id(&args.database_path) {
-        Ok(Some(id)) => {
-            println!("✅ 成功获取 session_id");
-            id
+/// 读取账号配置
+///
+/// 指定 `--profile` 或 `--profiles`，或默认的配置文件存在且未指定 Cookie 相关参数时读取配置文件；
+/// 未指定 `--profile` 时使用配置文件中的默认配置
+fn load_profile(args: &SessionArgs) -> Result<Option<Profile>, IflyrecError> {
+    let cookie_args_given =
+        args.key_password.is_some() || args.cookie_name.is_some() || args.cookie_host.is_some();
+    let path = match (&args.profiles_path, ProfileConfig::default_path()) {
+        (Some(path), _) => PathBuf::from(path),
+        (None, Some(path)) if args.profile.is_some() || (path.exists() && !cookie_args_given) => {
+            path
         }
-        Ok(None) => {
-            return Err(IflyrecError::AuthError("未找到有效的 session_id".to_string()));
-        }
-        Err(e) => {
-            return Err(IflyrecError::DatabaseError(e));
+        (None, _) if args.profile.is_some() => {
+            return Err(IflyrecError::InvalidParameter(
+                "cannot determine the profiles file path".to_string(),
+            ));
         }
+        (None, _) => return Ok(None),
     };
+    let config = ProfileConfig::load(&path)?;
+    let (name, profile) = config.profile(args.profile.as_deref())?;
+    eprintln!("账号配置: {name}（{}）", path.display());
+    Ok(Some(profile.clone()))
+}
 
-    // 创建客户端
-    let client = IflyrecClient::new(session_id);
+/// 由命令行参数构建 Cookie 数据库和 Firefox 的会话来源
+fn build_cookie_sessions(args: &SessionArgs) -> (CookieDbSession, FirefoxSession) {
+    let mut cookie_session = CookieDbSession::new(&args.database_path);
+    let mut firefox_session = FirefoxSession::new();
+    if let Some(password) = &args.key_password {
+        cookie_session = cookie_session.key_password(password);
+    }
+    if let Some(name) = &args.cookie_name {
+        cookie_session = cookie_session.cookie_name(name);
+        firefox_session = firefox_session.cookie_name(name);
+    }
+    if let Some(host) = &args.cookie_host {
+        cookie_session = cookie_session.host(host);
+        firefox_session = firefox_session.host(host);
+    }
+    (cookie_session, firefox_session)
+}
 
-    let order_id = if let Some(order_id) = args.order_id {
-        order_id
-    } else {
-        println!("--- 开始转录任务 ---");
-        let audio_file = args.audio_file.as_ref().unwrap();
-        println!("音频文件: {audio_file}");
+/// 构建会话ID来源
+///
+/// 指定账号配置时使用配置中的来源；否则优先使用环境变量中的 session_id，
+/// 其次从 Cookie 数据库提取，最后查找 Firefox 配置文件
+fn build_session_provider(args: &SessionArgs, profile: Option<&Profile>) -> SessionChain {
+    match profile {
+        Some(profile) => profile.session_provider(),
+        None => {
+            let (cookie_session, firefox_session) = build_cookie_sessions(args);
+            SessionChain::new()
+                .with(EnvSession::default())
+                .with(cookie_session)
+                .with(firefox_session)
+        }
+    }
+}
 
-        if let Some(ref task_name) = args.task_name {
-            println!("任务名称: {task_name}");
+/// 构建在终端显示进度条的进度报告器
+fn build_progress_sink() -> ProgressSink {
+    let bar = ProgressBar::no_length();
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{spinner} [{elapsed_precise}] [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+        )
+        .expect("valid progress template")
+        .progress_chars("=> "),
+    );
+
+    ProgressSink::new(move |event: &ProgressEvent| match event {
+        ProgressEvent::MetadataHandshake => bar.println("正在获取 file_id..."),
+        ProgressEvent::UploadStarted {
+            uploaded_bytes,
+            total_bytes,
+            ..
+        } => {
+            bar.set_length(*total_bytes);
+            bar.set_position(*uploaded_bytes);
+        }
+        ProgressEvent::BlockUploaded { uploaded_bytes, .. } => bar.set_position(*uploaded_bytes),
+        ProgressEvent::UploadCompleted { .. } => {
+            bar.finish();
+            eprintln!("✅ 音频上传成功！");
         }
+        ProgressEvent::CalculatingDuration => eprintln!("正在计算音频时长..."),
+        ProgressEvent::SubmittingOrder => eprintln!("正在提交转录订单..."),
+        ProgressEvent::OrderSubmitted { .. } => {}
+        ProgressEvent::OrderStateChanged { state, .. } => match state {
+            OrderState::NotListed => eprintln!("⏳ 订单尚未出现在订单列表中..."),
+            OrderState::Processing(status) => eprintln!("⏳ 转录任务正在进行中（{status}）..."),
+            OrderState::Completed => eprintln!("✅ 转录任务完成！"),
+        },
+    })
+}
+
+/// 隐藏会话ID的中间部分
+fn mask_session_id(session_id: &str) -> String {
+    let chars: Vec<char> = session_id.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let head: String = chars[..4].iter().collect();
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{head}...{tail}")
+}
+
+/// 以相对当前的时间描述时间点
+fn describe_time(time: Option<SystemTime>) -> String {
+    let Some(time) = time else {
+        return "未知".to_string();
+    };
+    let (duration, suffix) = match SystemTime::now().duration_since(time) {
+        Ok(elapsed) => (elapsed, "前"),
+        Err(e) => (e.duration(), "后"),
+    };
+    let secs = duration.as_secs();
+    let amount = match secs {
+        0..60 => format!("{secs} 秒"),
+        60..3600 => format!("{} 分钟", secs / 60),
+        3600..86400 => format!("{} 小时", secs / 3600),
+        _ => format!("{} 天", secs / 86400),
+    };
+    format!("{amount}{suffix}")
+}
+
+/// 格式化毫秒时间，`srt` 为真时附带 SRT 格式的毫秒
+fn format_timestamp(ms: i64, srt: bool) -> String {
+    let ms = ms.max(0);
+    let (hours, minutes, seconds) = (ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60);
+    if srt {
+        format!("{hours:02}:{minutes:02}:{seconds:02},{:03}", ms % 1000)
+    } else {
+        format!("{hours:02}:{minutes:02}:{seconds:02}")
+    }
+}
+
+/// 打印一组候选会话 Cookie
+fn print_session_cookies(cookies: &[SessionCookie]) {
+    if cookies.is_empty() {
+        println!("  （无）");
+    }
+    for cookie in cookies {
+        let state = if cookie.is_expired() {
+            "❌ 已过期"
+        } else {
+            "✅ 有效"
+        };
+        let expires = match cookie.expires {
+            Some(_) => describe_time(cookie.expires),
+            None => "浏览器会话结束时".to_string(),
+        };
+        println!(
+            "  {state} {}={} ({})",
+            cookie.name,
+            mask_session_id(&cookie.value),
+            cookie.host
+        );
+        println!(
+            "      最近访问: {}  创建: {}  过期: {expires}",
+            describe_time(cookie.last_access),
+            describe_time(cookie.created)
+        );
+        println!("      来源: {}", cookie.source.display());
+    }
+}
 
-        if args.need_sms {
-            println!("短信通知: 已启用");
+/// 列出所有候选会话及将要使用的会话
+fn list_sessions(session_provider: &SessionChain, args: &SessionArgs, profile: Option<&Profile>) {
+    if profile.is_none() {
+        let (cookie_session, firefox_session) = build_cookie_sessions(args);
+        match EnvSession::default().session_id() {
+            Ok(session_id) => println!(
+                "环境变量 {DEFAULT_SESSION_ENV}: {}",
+                mask_session_id(&session_id)
+            ),
+            Err(_) => println!("环境变量 {DEFAULT_SESSION_ENV}: 未设置"),
         }
 
-        if let Some(ref hot_words) = args.hot_words {
-            println!("热词设置成功: {hot_words}");
+        println!("Cookie 数据库:");
+        match cookie_session.cookies() {
+            Ok(cookies) => print_session_cookies(&cookies),
+            Err(e) => println!("  ❌ 无法读取：{e}"),
         }
 
-        println!("语言设置: {}", args.language);
-        println!("数据库路径: {}", args.database_path);
+        println!("Firefox 配置文件:");
+        print_session_cookies(&firefox_session.cookies());
+    }
 
-        // 构建转录选项
-        let options = build_transcription_options(&args);
+    match session_provider.session_id() {
+        Ok(session_id) => println!("将使用的 session_id: {}", mask_session_id(&session_id)),
+        Err(e) => println!("❌ 没有可用的 session_id：{e}"),
+    }
+}
+
+/// 上传音频文件
+///
+/// 指定 `--resume` 时，上传进度记录在清单中以便中断后续传
+async fn upload(
+    client: &IflyrecClient,
+    args: &UploadArgs,
+    progress: &ProgressSink,
+) -> Result<String, IflyrecError> {
+    eprintln!("音频文件: {}", args.audio_file);
+    if let Some(task_name) = &args.task_name {
+        eprintln!("任务名称: {task_name}");
+    }
 
-        // 提交转录任务
-        let order_id = client
-            .initiate_transcription_task(audio_file, args.task_name, options)
-            .await?;
-            
-        println!("✅ 转录任务提交成功！");
-        println!("订单 ID: {order_id}");
-        order_id
+    let manifest_path = UploadManifest::default_path(Path::new(&args.audio_file));
+    let preprocess = if args.normalize_audio {
+        AudioPreprocess::Auto
+    } else {
+        AudioPreprocess::None
     };
-    println!("Waiting for the result...");
-    println!("Program will exit after 1 minute.");
-    let retry_strategy = ExponentialBackoff::from_millis(500)
-        .max_delay(Duration::from_secs(10))
-        .take(5); // 最多重试5次
-
-    let order = Retry::spawn(retry_strategy, || async {
-        match client.get_order(&order_id).await {
-            Ok(order_option) => match order_option {
-                Some(order) => {
-                    println!("✅ 转录任务完成！");
-                    Ok::<TranscriptionOrder, IflyrecError>(order)
-                }
-                None => Err(IflyrecError::OrderProcessing("⏳ 转录任务正在进行中...".to_string())),
-            },
-            Err(e) => Err(e),
+    let upload_options = UploadOptions {
+        progress: Some(progress.clone()),
+        preprocess,
+        ..UploadOptions::default()
+    };
+
+    if args.resume && manifest_path.exists() {
+        eprintln!("续传清单: {}", manifest_path.display());
+        client.resume_upload(&manifest_path, upload_options).await
+    } else {
+        if args.resume {
+            eprintln!("未找到上传清单，将重新上传");
         }
-    })
-    .await?;
+        let upload_options = UploadOptions {
+            manifest_path: args.resume.then_some(manifest_path),
+            ..upload_options
+        };
+        client
+            .upload_audio_file_with_options(
+                &args.audio_file,
+                args.task_name.clone(),
+                upload_options,
+            )
+            .await
+    }
+}
+
+/// 提交转录任务
+async fn submit(
+    client: &IflyrecClient,
+    file_id: &str,
+    args: &TranscriptionArgs,
+    profile: Option<&Profile>,
+    progress: &ProgressSink,
+) -> Result<String, IflyrecError> {
+    let options = build_transcription_options(args, profile.and_then(|p| p.options.as_ref()));
+    if let Some(options) = &options {
+        eprintln!("语言设置: {}", options.language);
+        if options.need_sms {
+            eprintln!("短信通知: 已启用");
+        }
+        if !options.hot_words.is_empty() {
+            eprintln!("热词设置: {}", options.hot_words);
+        }
+    }
+
+    let order_id = client
+        .submit_uploaded_audio(file_id, options, Some(progress))
+        .await?;
+    eprintln!("✅ 转录任务提交成功！订单 ID: {order_id}");
+    Ok(order_id)
+}
 
-    if order.order_status == "completed" {
-        let result = client.get_order_result(&order).await?;
+/// 获取已完成的订单，未完成时返回 [`IflyrecError::OrderProcessing`]
+async fn completed_order(
+    client: &IflyrecClient,
+    order_id: &str,
+) -> Result<TranscriptionOrder, IflyrecError> {
+    let order = client.get_order(order_id).await?;
+    match OrderState::of(Some(&order)) {
+        OrderState::Completed => Ok(order),
+        state => Err(IflyrecError::OrderProcessing(format!(
+            "{order_id} ({state})"
+        ))),
+    }
+}
+
+/// 按格式渲染转写结果
+fn render_transcript(
+    result: &TranscriptResult,
+    format: ExportFormat,
+) -> Result<String, IflyrecError> {
+    if let ExportFormat::Json = format {
+        let mut output = serde_json::to_string_pretty(result)?;
+        output.push('\n');
+        return Ok(output);
+    }
 
-        let mut output_buffer = String::new();
+    let role_names: HashMap<&str, &str> = result
+        .roles
+        .iter()
+        .filter(|role| !role.name.is_empty())
+        .map(|role| (role.role.as_str(), role.name.as_str()))
+        .collect();
 
-        for paragraph in result.paragraphs {
-            let words = paragraph.words;
-            for word in words {
-                output_buffer.push_str(&word.text);
+    let mut output = String::new();
+    for (index, paragraph) in result.paragraphs.iter().enumerate() {
+        let text: String = paragraph
+            .words
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect();
+        let start = paragraph
+            .paragraph_time
+            .first()
+            .copied()
+            .unwrap_or_default();
+        let end = paragraph.paragraph_time.get(1).copied().unwrap_or(start);
+        // 写入 String 不会失败
+        let _ = match format {
+            ExportFormat::Txt => writeln!(output, "{text}\n"),
+            ExportFormat::Md => {
+                let speaker = match role_names.get(paragraph.role.as_str()) {
+                    Some(name) => name.to_string(),
+                    None => format!("说话人 {}", paragraph.role),
+                };
+                writeln!(
+                    output,
+                    "**{speaker}** [{}]\n\n{text}\n",
+                    format_timestamp(start, false)
+                )
             }
-            output_buffer.push_str("\n\n");
+            ExportFormat::Srt => writeln!(
+                output,
+                "{}\n{} --> {}\n{text}\n",
+                index + 1,
+                format_timestamp(start, true),
+                format_timestamp(end, true)
+            ),
+            ExportFormat::Json => unreachable!("handled above"),
+        };
+    }
+    Ok(output)
+}
+
+/// 下载转写结果并写入文件
+async fn export(
+    client: &IflyrecClient,
+    order: &TranscriptionOrder,
+    args: &OutputArgs,
+) -> Result<(), IflyrecError> {
+    let result = client.get_order_result(order).await?;
+    let content = render_transcript(&result, args.format)?;
+
+    let output = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from(format!("{}.{}", order.order_name, args.format.extension()))
+    });
+    if output == Path::new("-") {
+        std::io::stdout().write_all(content.as_bytes())?;
+    } else {
+        let mut output_file = std::fs::File::create(&output)?;
+        output_file.write_all(content.as_bytes())?;
+        eprintln!("✅ 转录结果已保存到文件: {}", output.display());
+        println!("{}", output.display());
+    }
+    Ok(())
+}
+
+/// 打印一个订单（制表符分隔：订单ID、状态、音频时长、创建时间、名称）
+fn print_order(order: &TranscriptionOrder) {
+    let created = u64::try_from(order.create_time)
+        .ok()
+        .filter(|ms| *ms > 0)
+        .map(|ms| UNIX_EPOCH + Duration::from_millis(ms));
+    println!(
+        "{}\t{}\t{}\t{}\t{}",
+        order.order_id,
+        OrderState::of(Some(order)),
+        format_timestamp(order.audio_durations, false),
+        describe_time(created),
+        order.order_name
+    );
+}
+
+async fn run(cli: Cli) -> Result<(), IflyrecError> {
+    let profile = load_profile(&cli.session)?;
+    let session_provider = build_session_provider(&cli.session, profile.as_ref());
+
+    if let Command::Session { check } = cli.command {
+        list_sessions(&session_provider, &cli.session, profile.as_ref());
+        if check {
+            let client = IflyrecClient::from_session_provider(&session_provider)?;
+            client.validate_session().await?;
+            println!("✅ session_id 有效");
+        }
+        return Ok(());
+    }
+
+    // 创建客户端
+    let client = IflyrecClient::from_session_provider(&session_provider)?;
+    let progress = build_progress_sink();
+
+    match cli.command {
+        Command::Upload(args) => {
+            // 提前校验会话，避免上传完成后才发现会话失效
+            client.validate_session().await?;
+            let file_id = upload(&client, &args, &progress).await?;
+            println!("{file_id}");
+        }
+        Command::Submit { file_id, options } => {
+            let order_id = submit(&client, &file_id, &options, profile.as_ref(), &progress).await?;
+            println!("{order_id}");
         }
+        Command::Transcribe {
+            upload: upload_args,
+            options,
+            wait,
+            output,
+        } => {
+            client.validate_session().await?;
+            let file_id = upload(&client, &upload_args, &progress).await?;
+            let order_id = submit(&client, &file_id, &options, profile.as_ref(), &progress).await?;
+            println!("{order_id}");
 
-        // 写入文件
-        let output_file_name = format!("{}.txt", order.order_name);
-        let output_file_path = std::path::Path::new(&output_file_name);
-        let mut output_file = std::fs::File::create(output_file_path)?;
-        output_file.write_all(output_buffer.as_bytes())?;
-        println!("✅ 转录结果已保存到文件: {output_file_name}");
+            eprintln!("等待转录完成...");
+            let order = client
+                .wait_for_completion(&order_id, build_wait_options(&wait, progress))
+                .await?;
+            export(&client, &order, &output).await?;
+        }
+        Command::Status {
+            order_id,
+            wait_for_completion,
+            wait,
+        } => {
+            let order = if wait_for_completion {
+                client
+                    .wait_for_completion(&order_id, build_wait_options(&wait, progress))
+                    .await?
+            } else {
+                client.get_order(&order_id).await?
+            };
+            print_order(&order);
+        }
+        Command::List { limit, all } => {
+            if all || limit.is_some() {
+                let mut orders = Box::pin(client.list_orders().take(limit.unwrap_or(usize::MAX)));
+                while let Some(order) = orders.try_next().await? {
+                    print_order(&order);
+                }
+            } else {
+                for order in client.get_recent_orders().await? {
+                    print_order(&order);
+                }
+            }
+        }
+        Command::Download { order_id, output } => {
+            let order = completed_order(&client, &order_id).await?;
+            let output = OutputArgs {
+                format: ExportFormat::Txt,
+                output,
+            };
+            export(&client, &order, &output).await?;
+        }
+        Command::Export { order_id, output } => {
+            let order = completed_order(&client, &order_id).await?;
+            export(&client, &order, &output).await?;
+        }
+        Command::Session { .. } => unreachable!("handled above"),
     }
 
     Ok(())
 }
+
+#[tokio::main]
+async fn main() -> Result<(), IflyrecError> {
+    let result = run(Cli::parse()).await;
+    if let Err(e) = &result
+        && e.is_auth_error()
+    {
+        eprintln!("❌ session_id 无效或已失效，请在讯飞听见客户端重新登录");
+    }
+    result
+}
@@ -1,3 +1,6 @@
+use crate::api::{OrderState, SegmentOrder};
+
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -5,6 +8,12 @@ pub enum IflyrecError {
     #[error("API Error: {code} - {desc}")]
     ApiError { code: String, desc: String },
 
+    #[error("Audio duration still calculating: {code} - {desc}")]
+    DurationCalculating { code: String, desc: String },
+
+    #[error("HTTP {status}: {reason}")]
+    HttpStatus { status: u16, reason: String },
+
     #[error("HTTP Request Failed: {0}")]
     RequestError(#[from] reqwest::Error),
 
@@ -17,12 +26,34 @@ pub enum IflyrecError {
     #[error("Order still processing: {0}")]
     OrderProcessing(String),
 
+    #[error("Order not found: {0}")]
+    OrderNotFound(String),
+
+    #[error("Timed out waiting for order {order_id} (last state: {last_state})")]
+    OrderTimeout {
+        order_id: String,
+        last_state: OrderState,
+    },
+
+    #[error("Long audio transcription failed after submitting {} segment orders: {source}", .segments.len())]
+    LongAudioFailed {
+        /// 已提交的分段订单，可用于继续等待结果
+        segments: Vec<SegmentOrder>,
+        source: Box<IflyrecError>,
+    },
+
+    #[error("Order watcher stopped: {0}")]
+    WatcherStopped(Arc<IflyrecError>),
+
     #[error("Failed to get order ID")]
     OrderIdUnavailable,
 
     #[error("Duration calculation failed: {0}")]
     DurationCalculationError(String),
 
+    #[error("Audio transcoding failed: {0}")]
+    TranscodeError(String),
+
     #[error("Upload failed: {0}")]
     UploadError(String),
 
@@ -32,6 +63,9 @@ pub enum IflyrecError {
     #[error("Invalid parameter: {0}")]
     InvalidParameter(String),
 
+    #[error("Cookie decryption failed: {0}")]
+    CookieDecryptionError(String),
+
     #[error("Database error: {0}")]
     DatabaseError(#[from] rusqlite::Error),
 
@@ -39,13 +73,141 @@ pub enum IflyrecError {
     Unknown(String),
 }
 
+impl IflyrecError {
+    /// 是否为认证失败，需要重新登录获取会话ID
+    ///
+    /// 本地找不到会话ID和服务器拒绝会话均为 [`Self::AuthError`]
+    pub fn is_auth_error(&self) -> bool {
+        match self {
+            Self::AuthError(_) => true,
+            Self::WatcherStopped(e) => e.is_auth_error(),
+            Self::LongAudioFailed { source, .. } => source.is_auth_error(),
+            _ => false,
+        }
+    }
+
+    /// 错误是否为暂时性的，重试可能成功
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::DurationCalculating { .. } => true,
+            Self::HttpStatus { status, .. } => *status == 429 || *status >= 500,
+            Self::RequestError(e) => {
+                e.is_timeout()
+                    || e.is_connect()
+                    || e.is_request()
+                    || e.is_body()
+                    || e.status().is_some_and(|status| status.is_server_error())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 已知错误的构造函数
+type ErrorConstructor = fn(String, String) -> IflyrecError;
+
+/// 已知错误目录
+///
+/// 接口的错误码没有公开文档，只收录实际观察到的错误；错误码未知的错误按描述中的完整短语匹配，
+/// 未命中的错误返回 [`IflyrecError::ApiError`]。会话失效由 HTTP 401/403 或客户端配置的错误码识别
+const ERROR_CATALOGUE: &[(&str, ErrorConstructor)] = &[
+    // 提交订单时服务器尚未算出音频时长
+    ("订单音频时长计算中", |code, desc| {
+        IflyrecError::DurationCalculating { code, desc }
+    }),
+];
+
 /// 用于处理API响应中的错误码
 pub fn map_api_error(code: &str, desc: &str) -> IflyrecError {
-    match code {
-        "000000" => IflyrecError::Unknown(format!("Unexpected error mapping for success code: {desc}")),
-        _ => IflyrecError::ApiError {
+    if code == "000000" {
+        return IflyrecError::Unknown(format!("Unexpected error mapping for success code: {desc}"));
+    }
+
+    let constructor = ERROR_CATALOGUE
+        .iter()
+        .find(|(phrase, _)| desc.contains(phrase))
+        .map(|(_, constructor)| *constructor);
+
+    match constructor {
+        Some(constructor) => constructor(code.to_string(), desc.to_string()),
+        None => IflyrecError::ApiError {
             code: code.to_string(),
             desc: desc.to_string(),
         },
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_catalogued_errors() {
+        assert!(matches!(
+            map_api_error("100001", "订单音频时长计算中，请稍后"),
+            IflyrecError::DurationCalculating { code, .. } if code == "100001"
+        ));
+    }
+
+    #[test]
+    fn unknown_errors_fall_back_to_api_error() {
+        for desc in ["系统繁忙，请稍后重试", "参数错误", ""] {
+            assert!(
+                matches!(
+                    map_api_error("999999", desc),
+                    IflyrecError::ApiError { code, desc: d } if code == "999999" && d == desc
+                ),
+                "{desc}"
+            );
+        }
+    }
+
+    #[test]
+    fn success_code_is_unexpected() {
+        assert!(matches!(
+            map_api_error("000000", "成功"),
+            IflyrecError::Unknown(_)
+        ));
+    }
+
+    fn http(status: u16) -> IflyrecError {
+        IflyrecError::HttpStatus {
+            status,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn classifies_retryable_errors() {
+        let (code, desc) = (String::new(), String::new());
+        let cases = [
+            (
+                IflyrecError::DurationCalculating {
+                    code: code.clone(),
+                    desc: desc.clone(),
+                },
+                true,
+            ),
+            (IflyrecError::ApiError { code, desc }, false),
+            (http(429), true),
+            (http(500), true),
+            (http(503), true),
+            (http(401), false),
+            (http(404), false),
+            (IflyrecError::AuthError("no cookie".to_string()), false),
+            (IflyrecError::OrderNotFound("1".to_string()), false),
+        ];
+
+        for (error, retryable) in cases {
+            assert_eq!(error.is_retryable(), retryable, "{error:?}");
+        }
+    }
+
+    #[test]
+    fn auth_errors_are_detected_through_wrappers() {
+        let expired = IflyrecError::AuthError("session rejected: HTTP 401".to_string());
+        assert!(expired.is_auth_error());
+        assert!(IflyrecError::WatcherStopped(Arc::new(expired)).is_auth_error());
+        assert!(!IflyrecError::OrderNotFound("1".to_string()).is_auth_error());
+    }
+}
@@ -5,9 +5,20 @@
 
 // 导出公共模块
 pub mod api;
+pub mod audio;
 pub mod error;
+pub mod profile;
+pub mod retry;
+pub mod session;
 pub(crate) mod util;
 
 // 重新导出常用类型，方便用户直接使用
-pub use api::{IflyrecClient, TranscriptionOptions, TranscriptionOrder, TranscriptResult};
+pub use api::{
+    Account, AccountPool, AudioPreprocess, IflyrecClient, IflyrecClientBuilder, LongAudioOptions,
+    LongAudioTranscript, OrderEvent, OrderState, OrderStatus, OrderUpdate, OrderWatcher,
+    ProgressEvent, ProgressReporter, ProgressSink, TranscriptResult, TranscriptionOptions,
+    TranscriptionOrder, UploadManifest, UploadOptions, WaitOptions,
+};
 pub use error::IflyrecError;
+pub use profile::{Profile, ProfileConfig};
+pub use retry::RetryPolicy;
@@ -0,0 +1,299 @@
+//! 账号配置
+//!
+//! 配置文件（JSON）将配置名映射到会话ID来源和默认的 [`TranscriptionOptions`]：
+//!
+//! ```json
+//! {
+//!   "default": "work",
+//!   "profiles": {
+//!     "work": {
+//!       "session": [{ "cookie_db": { "path": "work/Cookies" } }],
+//!       "options": { "language": "cn", "hot_words": "Rust，WebRTC" }
+//!     },
+//!     "personal": {
+//!       "session": [{ "env": "IFLYREC_PERSONAL_SESSION_ID" }, { "firefox": {} }]
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! 配置文件中的相对路径相对于配置文件所在目录
+
+use crate::api::{IflyrecClient, TranscriptionOptions};
+use crate::error::IflyrecError;
+use crate::session::{
+    CookieDbSession, EnvSession, FileSession, FirefoxSession, LiteralSession, SessionChain,
+    SessionProvider,
+};
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 指定配置文件路径的环境变量
+pub const DEFAULT_PROFILES_ENV: &str = "IFLYREC_PROFILES";
+
+/// 配置文件名
+const PROFILES_FILE_NAME: &str = "profiles.json";
+
+/// 会话ID来源
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionSource {
+    /// 直接给定的会话ID
+    SessionId(String),
+    /// 环境变量名
+    Env(String),
+    /// 保存会话ID的文本文件
+    File(PathBuf),
+    /// Chromium 或 Firefox 的 Cookie 数据库
+    CookieDb {
+        path: PathBuf,
+        #[serde(default)]
+        key_password: Option<String>,
+        #[serde(default)]
+        cookie_name: Option<String>,
+        #[serde(default)]
+        host: Option<String>,
+    },
+    /// 本机的 Firefox 配置文件，未指定 `profiles` 时自动查找
+    Firefox {
+        #[serde(default)]
+        profiles: Option<Vec<PathBuf>>,
+        #[serde(default)]
+        cookie_name: Option<String>,
+        #[serde(default)]
+        host: Option<String>,
+    },
+}
+
+impl SessionSource {
+    /// 创建对应的会话ID来源
+    pub fn provider(&self) -> Box<dyn SessionProvider> {
+        match self {
+            Self::SessionId(session_id) => Box::new(LiteralSession::new(session_id)),
+            Self::Env(var) => Box::new(EnvSession::new(var)),
+            Self::File(path) => Box::new(FileSession::new(path)),
+            Self::CookieDb {
+                path,
+                key_password,
+                cookie_name,
+                host,
+            } => {
+                let mut session = CookieDbSession::new(path);
+                if let Some(password) = key_password {
+                    session = session.key_password(password);
+                }
+                if let Some(name) = cookie_name {
+                    session = session.cookie_name(name);
+                }
+                if let Some(host) = host {
+                    session = session.host(host);
+                }
+                Box::new(session)
+            }
+            Self::Firefox {
+                profiles,
+                cookie_name,
+                host,
+            } => {
+                let mut session = match profiles {
+                    Some(profiles) => FirefoxSession::with_profiles(profiles),
+                    None => FirefoxSession::new(),
+                };
+                if let Some(name) = cookie_name {
+                    session = session.cookie_name(name);
+                }
+                if let Some(host) = host {
+                    session = session.host(host);
+                }
+                Box::new(session)
+            }
+        }
+    }
+
+    /// 将相对路径解析为相对于 `base_dir` 的路径
+    fn resolve_paths(&mut self, base_dir: &Path) {
+        let resolve = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = base_dir.join(&*path);
+            }
+        };
+        match self {
+            Self::File(path) | Self::CookieDb { path, .. } => resolve(path),
+            Self::Firefox {
+                profiles: Some(profiles),
+                ..
+            } => profiles.iter_mut().for_each(resolve),
+            _ => {}
+        }
+    }
+}
+
+/// 账号配置
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Profile {
+    /// 会话ID来源，按顺序尝试
+    #[serde(default)]
+    pub session: Vec<SessionSource>,
+    /// 默认的转录选项
+    #[serde(default)]
+    pub options: Option<TranscriptionOptions>,
+}
+
+impl Profile {
+    /// 由各个来源组成的会话ID回退链
+    pub fn session_provider(&self) -> SessionChain {
+        self.session
+            .iter()
+            .fold(SessionChain::new(), |chain, source| {
+                chain.with(source.provider())
+            })
+    }
+
+    /// 使用该配置的会话ID创建客户端
+    pub fn client(&self) -> Result<IflyrecClient, IflyrecError> {
+        IflyrecClient::from_session_provider(&self.session_provider())
+    }
+}
+
+/// 配置文件
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ProfileConfig {
+    /// 未指定配置名时使用的配置
+    #[serde(default)]
+    pub default: Option<String>,
+    /// 配置名到账号配置的映射
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+impl ProfileConfig {
+    /// 默认的配置文件路径
+    ///
+    /// 依次使用环境变量 `IFLYREC_PROFILES`、`$XDG_CONFIG_HOME/iflypen/profiles.json`、
+    /// `%APPDATA%\iflypen\profiles.json` 和 `~/.config/iflypen/profiles.json`
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(DEFAULT_PROFILES_ENV) {
+            return Some(PathBuf::from(path));
+        }
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .or_else(|| std::env::var_os("APPDATA"))
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_dir.join("iflypen").join(PROFILES_FILE_NAME))
+    }
+
+    /// 读取配置文件，相对路径解析为相对于配置文件所在目录
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IflyrecError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let mut config: Self = serde_json::from_str(&content)?;
+
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        for profile in config.profiles.values_mut() {
+            for source in &mut profile.session {
+                source.resolve_paths(base_dir);
+            }
+        }
+        Ok(config)
+    }
+
+    /// 按名称查找配置
+    ///
+    /// 未指定名称时使用 `default`；没有 `default` 且只有一个配置时使用该配置
+    pub fn profile(&self, name: Option<&str>) -> Result<(&str, &Profile), IflyrecError> {
+        let name = match name.or(self.default.as_deref()) {
+            Some(name) => name,
+            None if self.profiles.len() == 1 => self.profiles.keys().next().unwrap(),
+            None => {
+                return Err(IflyrecError::InvalidParameter(
+                    "no profile specified and no default profile configured".to_string(),
+                ));
+            }
+        };
+        self.profiles
+            .get_key_value(name)
+            .map(|(name, profile)| (name.as_str(), profile))
+            .ok_or_else(|| IflyrecError::InvalidParameter(format!("unknown profile: {name}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::temporary_path;
+
+    fn config(json: serde_json::Value) -> ProfileConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn load_resolves_relative_paths_against_config_dir() {
+        let dir = temporary_path("");
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join(PROFILES_FILE_NAME);
+        let absolute = std::env::temp_dir().join("Cookies");
+        let content = serde_json::json!({
+            "profiles": {
+                "work": {
+                    "session": [
+                        { "file": "session.txt" },
+                        { "cookie_db": { "path": absolute } },
+                        { "firefox": { "profiles": ["firefox/default"] } }
+                    ]
+                }
+            }
+        });
+        std::fs::write(&path, content.to_string()).unwrap();
+
+        let loaded = ProfileConfig::load(&path);
+        std::fs::remove_dir_all(&dir).unwrap();
+        let loaded = loaded.unwrap();
+
+        let session = &loaded.profiles["work"].session;
+        assert!(matches!(&session[0], SessionSource::File(p) if *p == dir.join("session.txt")));
+        assert!(matches!(&session[1], SessionSource::CookieDb { path, .. } if *path == absolute));
+        assert!(matches!(
+            &session[2],
+            SessionSource::Firefox { profiles: Some(profiles), .. }
+                if profiles == &[dir.join("firefox/default")]
+        ));
+    }
+
+    #[test]
+    fn falls_back_to_default_profile() {
+        let config = config(serde_json::json!({
+            "default": "personal",
+            "profiles": { "work": {}, "personal": {} }
+        }));
+        assert_eq!(config.profile(None).unwrap().0, "personal");
+        assert_eq!(config.profile(Some("work")).unwrap().0, "work");
+        assert!(config.profile(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn single_profile_is_used_without_default() {
+        let single = config(serde_json::json!({ "profiles": { "work": {} } }));
+        assert_eq!(single.profile(None).unwrap().0, "work");
+
+        let ambiguous = config(serde_json::json!({ "profiles": { "work": {}, "personal": {} } }));
+        assert!(ambiguous.profile(None).is_err());
+    }
+
+    #[test]
+    fn profile_session_sources_form_a_chain() {
+        let config = config(serde_json::json!({
+            "profiles": {
+                "work": {
+                    "session": [
+                        { "env": "IFLYPEN_TEST_UNSET_SESSION_ID" },
+                        { "session_id": "abc" }
+                    ]
+                }
+            }
+        }));
+        let (_, profile) = config.profile(None).unwrap();
+        assert_eq!(profile.session_provider().session_id().unwrap(), "abc");
+    }
+}
@@ -0,0 +1,265 @@
+//! 重试策略
+//!
+//! 客户端的上传、时长计算、订单提交和结果获取均按 [`RetryPolicy`] 重试，
+//! 通过 [`IflyrecClientBuilder::retry_policy`] 设置默认策略，
+//! 或通过 [`IflyrecClient::with_retry_policy`] 为单次调用覆盖。
+//! 订单提交不是幂等操作，只在服务器明确拒绝（如时长计算中）或连接未建立时重试，
+//! 超时等无法确定请求是否已送达的错误不会重试，以免重复下单。
+//!
+//! [`IflyrecClientBuilder::retry_policy`]: crate::IflyrecClientBuilder::retry_policy
+//! [`IflyrecClient::with_retry_policy`]: crate::IflyrecClient::with_retry_policy
+
+use crate::error::IflyrecError;
+
+use rand::Rng;
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::time::{Duration, sleep};
+
+/// 判断错误是否应当重试
+type RetryPredicate = Arc<dyn Fn(&IflyrecError) -> bool + Send + Sync>;
+
+/// 重试策略
+///
+/// 第 n 次重试前等待 `initial_delay * multiplier^(n-1)`，不超过 `max_delay`
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// 首次重试前的等待时间
+    pub initial_delay: Duration,
+    /// 每次重试后等待时间的增长倍数
+    pub multiplier: f64,
+    /// 等待时间的上限
+    pub max_delay: Duration,
+    /// 最多尝试次数（包括首次请求），为 1 时不重试
+    pub max_attempts: usize,
+    /// 随机抖动比例（0 到 1），实际等待时间在 `[delay * (1 - jitter), delay]` 之间
+    pub jitter: f64,
+    /// 自定义的重试条件，未设置时使用 [`IflyrecError::is_retryable`]
+    retry_if: Option<RetryPredicate>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(10),
+            max_attempts: 5,
+            jitter: 0.1,
+            retry_if: None,
+        }
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("initial_delay", &self.initial_delay)
+            .field("multiplier", &self.multiplier)
+            .field("max_delay", &self.max_delay)
+            .field("max_attempts", &self.max_attempts)
+            .field("jitter", &self.jitter)
+            .field("retry_if", &self.retry_if.as_ref().map(|_| "<predicate>"))
+            .finish()
+    }
+}
+
+impl RetryPolicy {
+    /// 不重试的策略
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Self::default()
+        }
+    }
+
+    /// 设置自定义的重试条件
+    pub fn retry_if(
+        mut self,
+        predicate: impl Fn(&IflyrecError) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.retry_if = Some(Arc::new(predicate));
+        self
+    }
+
+    /// 错误是否应当重试
+    pub fn should_retry(&self, error: &IflyrecError) -> bool {
+        match &self.retry_if {
+            Some(predicate) => predicate(error),
+            None => error.is_retryable(),
+        }
+    }
+
+    /// 第 `retry` 次重试（从 1 开始）前的等待时间，不含抖动
+    ///
+    /// 结果限制在 0 到 `max_delay` 之间，参数无效导致结果为 NaN 时取 `max_delay`
+    pub fn delay(&self, retry: usize) -> Duration {
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let delay = self.initial_delay.as_secs_f64() * self.multiplier.powi(exponent);
+        let max_delay = self.max_delay.as_secs_f64();
+        if delay.is_nan() {
+            return self.max_delay;
+        }
+        Duration::from_secs_f64(delay.clamp(0.0, max_delay))
+    }
+
+    /// 用于非幂等请求的策略
+    ///
+    /// 在原有重试条件之外，要求请求确定未被服务器处理
+    pub(crate) fn for_non_idempotent(&self) -> Self {
+        let base = self.clone();
+        Self {
+            retry_if: Some(Arc::new(move |error| {
+                base.should_retry(error) && !may_have_been_processed(error)
+            })),
+            ..self.clone()
+        }
+    }
+
+    /// 校验策略参数
+    fn validate(&self) -> Result<(), IflyrecError> {
+        if self.max_attempts == 0 {
+            return Err(IflyrecError::InvalidParameter(
+                "max_attempts must be greater than 0".to_string(),
+            ));
+        }
+        let valid_multiplier = self.multiplier >= 1.0;
+        if !valid_multiplier || !(0.0..=1.0).contains(&self.jitter) {
+            return Err(IflyrecError::InvalidParameter(
+                "multiplier must be at least 1 and jitter between 0 and 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 按策略执行操作，失败且满足重试条件时等待后重试
+    pub(crate) async fn run<T, F, Fut>(&self, mut operation: F) -> Result<T, IflyrecError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, IflyrecError>>,
+    {
+        self.validate()?;
+
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(e) if attempt < self.max_attempts && self.should_retry(&e) => {
+                    let delay = self.jittered_delay(attempt);
+                    tracing::debug!("第 {attempt} 次尝试失败，{delay:?} 后重试：{e}");
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn jittered_delay(&self, retry: usize) -> Duration {
+        let delay = self.delay(retry);
+        if self.jitter <= 0.0 {
+            return delay;
+        }
+        let factor = 1.0 - self.jitter * rand::rng().random::<f64>();
+        delay.mul_f64(factor)
+    }
+}
+
+/// 请求是否可能已被服务器处理
+///
+/// 服务器明确返回的业务错误与未建立连接的错误都说明请求未被受理，其余传输错误无法确定
+fn may_have_been_processed(error: &IflyrecError) -> bool {
+    match error {
+        IflyrecError::RequestError(e) => !e.is_connect(),
+        IflyrecError::HttpStatus { status, .. } => *status != 429,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn duration_calculating() -> IflyrecError {
+        IflyrecError::DurationCalculating {
+            code: "999999".to_string(),
+            desc: "订单音频时长计算中".to_string(),
+        }
+    }
+
+    fn http_status(status: u16) -> IflyrecError {
+        IflyrecError::HttpStatus {
+            status,
+            reason: String::new(),
+        }
+    }
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(2), Duration::from_secs(1));
+        assert_eq!(policy.delay(3), Duration::from_secs(2));
+        assert_eq!(policy.delay(4), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn delay_is_capped_at_max_delay() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(5), Duration::from_secs(8));
+        assert_eq!(policy.delay(6), Duration::from_secs(10));
+        assert_eq!(policy.delay(usize::MAX), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn delay_treats_retry_zero_as_first_retry() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.delay(0), policy.delay(1));
+    }
+
+    #[test]
+    fn constant_delay_without_multiplier() {
+        let policy = RetryPolicy {
+            multiplier: 1.0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(500));
+        assert_eq!(policy.delay(10), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn delay_stays_within_bounds_for_invalid_multipliers() {
+        let policy = |multiplier| RetryPolicy {
+            multiplier,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy(-2.0).delay(2), Duration::ZERO);
+        assert_eq!(policy(f64::NAN).delay(2), Duration::from_secs(10));
+        assert_eq!(policy(f64::INFINITY).delay(2), Duration::from_secs(10));
+        assert_eq!(policy(2.0).delay(usize::MAX), Duration::from_secs(10));
+
+        let zero = RetryPolicy {
+            initial_delay: Duration::ZERO,
+            multiplier: f64::INFINITY,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(zero.delay(3), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn non_idempotent_policy_skips_ambiguous_failures() {
+        let policy = RetryPolicy::default().for_non_idempotent();
+        assert!(policy.should_retry(&duration_calculating()));
+        assert!(policy.should_retry(&http_status(429)));
+        assert!(!policy.should_retry(&http_status(502)));
+    }
+
+    #[test]
+    fn non_idempotent_policy_keeps_custom_predicate() {
+        let policy = RetryPolicy::default()
+            .retry_if(|_| false)
+            .for_non_idempotent();
+        assert!(!policy.should_retry(&duration_calculating()));
+    }
+}
@@ -0,0 +1,227 @@
+//! Chromium Cookie
+//!
+//! Linux 上的 Chromium/Electron 将 Cookie 加密后存入 `encrypted_value` 列：
+//! 以 `v10` 或 `v11` 为前缀，使用 AES-128-CBC 加密，密钥由口令经 PBKDF2-HMAC-SHA1 派生。
+//! `v10` 的口令固定为 `peanuts`，`v11` 的口令保存在系统密钥环中。
+
+use super::cookie_db::{CookieFilter, SessionCookie, unix_time_micros};
+use crate::error::IflyrecError;
+
+use aes::Aes128;
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, KeyIvInit};
+use rusqlite::Connection;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Chromium 在 Linux 上未使用密钥环时的默认口令
+pub const DEFAULT_CHROMIUM_KEY_PASSWORD: &str = "peanuts";
+
+/// 密钥派生使用的盐
+const SALT: &[u8] = b"saltysalt";
+/// 密钥派生的迭代次数
+const ITERATIONS: u32 = 1;
+/// 初始化向量（16 个空格）
+const IV: [u8; 16] = [b' '; 16];
+/// 新版本 Chromium 在明文前附加的域名哈希长度
+const HOST_HASH_LEN: usize = 32;
+
+/// Chromium Cookie 解密器
+#[derive(Clone, Debug)]
+pub struct ChromiumCookieDecryptor {
+    /// 按顺序尝试的密钥
+    keys: Vec<[u8; 16]>,
+}
+
+impl Default for ChromiumCookieDecryptor {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl ChromiumCookieDecryptor {
+    /// 创建解密器
+    ///
+    /// 依次尝试 `password`（如有）、默认口令 `peanuts` 和空口令，
+    /// `v11` Cookie 需传入密钥环中的口令（如 `secret-tool lookup application chromium` 的输出）
+    pub fn new(password: Option<&str>) -> Self {
+        let mut passwords: Vec<&str> = password.into_iter().collect();
+        for fallback in [DEFAULT_CHROMIUM_KEY_PASSWORD, ""] {
+            if !passwords.contains(&fallback) {
+                passwords.push(fallback);
+            }
+        }
+        Self {
+            keys: passwords.into_iter().map(derive_key).collect(),
+        }
+    }
+
+    /// 解密 `encrypted_value`
+    ///
+    /// `host_key` 用于去除新版本 Chromium 附加的域名哈希；没有版本前缀的值按明文处理
+    pub fn decrypt(&self, encrypted_value: &[u8], host_key: &str) -> Result<String, IflyrecError> {
+        let Some(ciphertext) = encrypted_value
+            .strip_prefix(b"v10")
+            .or_else(|| encrypted_value.strip_prefix(b"v11"))
+        else {
+            return String::from_utf8(encrypted_value.to_vec())
+                .map_err(|_| decryption_error("unknown cookie encryption version"));
+        };
+
+        let host_hash = Sha256::digest(host_key.as_bytes());
+        self.keys
+            .iter()
+            .filter_map(|key| {
+                cbc::Decryptor::<Aes128>::new(key.into(), &IV.into())
+                    .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+                    .ok()
+            })
+            .find_map(|plaintext| {
+                let value = match plaintext.strip_prefix(host_hash.as_slice()) {
+                    Some(value) if plaintext.len() >= HOST_HASH_LEN => value,
+                    _ => &plaintext,
+                };
+                String::from_utf8(value.to_vec()).ok()
+            })
+            .ok_or_else(|| decryption_error("no key password could decrypt the cookie"))
+    }
+}
+
+/// 由口令派生 AES-128 密钥
+fn derive_key(password: &str) -> [u8; 16] {
+    let mut key = [0u8; 16];
+    pbkdf2::pbkdf2_hmac::<Sha1>(password.as_bytes(), SALT, ITERATIONS, &mut key);
+    key
+}
+
+fn decryption_error(reason: &str) -> IflyrecError {
+    IflyrecError::CookieDecryptionError(reason.to_string())
+}
+
+/// Chromium 时间戳（自 1601-01-01 起的微秒数）与 Unix 时间戳的差值
+const WINDOWS_EPOCH_OFFSET_MICROS: i64 = 11_644_473_600_000_000;
+
+/// 读取 Chromium 格式数据库（`cookies` 表）中与 `filter` 匹配的会话 Cookie
+pub(super) fn read_session_cookies(
+    conn: &Connection,
+    decryptor: &ChromiumCookieDecryptor,
+    filter: &CookieFilter,
+) -> Result<Vec<SessionCookie>, IflyrecError> {
+    let mut stmt = conn.prepare(
+        "SELECT host_key, name, value, encrypted_value, expires_utc, last_access_utc, creation_utc FROM cookies",
+    )?;
+
+    let rows = stmt.query_map([], |row| {
+        let cookie = SessionCookie {
+            host: row.get(0)?,
+            name: row.get(1)?,
+            value: row.get(2)?,
+            expires: chromium_time(row.get(4)?),
+            last_access: chromium_time(row.get(5)?),
+            created: chromium_time(row.get(6)?),
+            source: PathBuf::new(),
+        };
+        let encrypted_value: Option<Vec<u8>> = row.get(3)?;
+        Ok((cookie, encrypted_value))
+    })?;
+
+    let mut cookies = Vec::new();
+    for row in rows {
+        let (mut cookie, encrypted_value) = row?;
+        if !filter.matches(&cookie.name, &cookie.host) {
+            continue;
+        }
+        if let Some(encrypted_value) = encrypted_value
+            && cookie.value.is_empty()
+            && !encrypted_value.is_empty()
+        {
+            match decryptor.decrypt(&encrypted_value, &cookie.host) {
+                Ok(value) => cookie.value = value,
+                Err(e) => {
+                    tracing::warn!("无法解密 {} 的 Cookie：{e}", cookie.host);
+                    continue;
+                }
+            }
+        }
+        if !cookie.value.is_empty() {
+            cookies.push(cookie);
+        }
+    }
+
+    Ok(cookies)
+}
+
+/// 将 Chromium 时间戳转换为系统时间，0 表示未设置
+fn chromium_time(micros: Option<i64>) -> Option<SystemTime> {
+    match micros? {
+        0 => None,
+        micros => unix_time_micros(micros - WINDOWS_EPOCH_OFFSET_MICROS),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 以 `peanuts` 派生的密钥加密的 `a1b2c3d4e5f6`
+    const PLAIN_V10: &str = "702f572233764d8c67730d08cf6098bb";
+    /// 以 `peanuts` 派生的密钥加密的 `SHA256(".iflyrec.com") || a1b2c3d4e5f6`
+    const HASHED_V10: &str = "f605a29add0213455b2e90efcac1acce57daa38892b14df7c49c925a4c361419\
+                              b458313004709c2c0707f82fb13bd8c0";
+
+    fn v10(hex: &str) -> Vec<u8> {
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap());
+        b"v10".iter().copied().chain(bytes).collect()
+    }
+
+    #[test]
+    fn derives_the_well_known_peanuts_key() {
+        assert_eq!(
+            derive_key(DEFAULT_CHROMIUM_KEY_PASSWORD),
+            [
+                0xfd, 0x62, 0x1f, 0xe5, 0xa2, 0xb4, 0x02, 0x53, 0x9d, 0xfa, 0x14, 0x7c, 0xa9, 0x27,
+                0x27, 0x78
+            ]
+        );
+    }
+
+    #[test]
+    fn decrypts_v10_without_host_hash() {
+        let decryptor = ChromiumCookieDecryptor::default();
+        let value = decryptor.decrypt(&v10(PLAIN_V10), ".iflyrec.com").unwrap();
+        assert_eq!(value, "a1b2c3d4e5f6");
+    }
+
+    #[test]
+    fn decrypts_v10_and_strips_host_hash() {
+        let decryptor = ChromiumCookieDecryptor::default();
+        let value = decryptor.decrypt(&v10(HASHED_V10), ".iflyrec.com").unwrap();
+        assert_eq!(value, "a1b2c3d4e5f6");
+    }
+
+    #[test]
+    fn falls_back_to_peanuts_after_custom_password() {
+        let decryptor = ChromiumCookieDecryptor::new(Some("keyring secret"));
+        let value = decryptor.decrypt(&v10(PLAIN_V10), ".iflyrec.com").unwrap();
+        assert_eq!(value, "a1b2c3d4e5f6");
+    }
+
+    #[test]
+    fn unprefixed_value_is_plaintext() {
+        let decryptor = ChromiumCookieDecryptor::default();
+        assert_eq!(decryptor.decrypt(b"plain", "").unwrap(), "plain");
+    }
+
+    #[test]
+    fn rejects_undecryptable_value() {
+        let decryptor = ChromiumCookieDecryptor::default();
+        assert!(matches!(
+            decryptor.decrypt(b"v10not a block", ".iflyrec.com"),
+            Err(IflyrecError::CookieDecryptionError(_))
+        ));
+    }
+}
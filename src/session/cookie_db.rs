@@ -0,0 +1,404 @@
+//! Cookie 数据库
+//!
+//! 自动识别 Chromium（`cookies` 表）和 Firefox（`moz_cookies` 表）格式的 Cookie 数据库
+
+use super::chromium::{self, ChromiumCookieDecryptor};
+use super::{SessionProvider, firefox};
+use crate::error::IflyrecError;
+use crate::util::temporary_path;
+
+use rusqlite::{Connection, ErrorCode, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// 默认匹配的 Cookie 名称片段
+const DEFAULT_NAME_PATTERN: &str = "session";
+/// 默认匹配的域名片段
+const DEFAULT_HOST_PATTERN: &str = "iflyrec";
+
+/// 从 Cookie 数据库中读取的会话 Cookie
+#[derive(Clone, Debug)]
+pub struct SessionCookie {
+    /// Cookie 名称
+    pub name: String,
+    /// 所属域名
+    pub host: String,
+    /// 会话ID
+    pub value: String,
+    /// 过期时间，未设置时为浏览器会话期间有效
+    pub expires: Option<SystemTime>,
+    /// 最近访问时间
+    pub last_access: Option<SystemTime>,
+    /// 创建时间
+    pub created: Option<SystemTime>,
+    /// 所在的数据库文件
+    pub source: PathBuf,
+}
+
+impl SessionCookie {
+    /// 是否已过期
+    pub fn is_expired(&self) -> bool {
+        self.expires
+            .is_some_and(|expires| expires <= SystemTime::now())
+    }
+
+    /// 新旧程度，越新越大：先比较最近访问时间，再比较创建时间
+    fn freshness(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        (self.last_access, self.created)
+    }
+}
+
+/// 按新旧程度排序，最新的在前
+pub(super) fn sort_freshest_first(cookies: &mut [SessionCookie]) {
+    cookies.sort_by_key(|cookie| std::cmp::Reverse(cookie.freshness()));
+}
+
+/// 取未过期的 Cookie 中最新的一个
+pub(super) fn freshest(cookies: &[SessionCookie]) -> Option<&SessionCookie> {
+    cookies
+        .iter()
+        .filter(|cookie| !cookie.is_expired())
+        .max_by_key(|cookie| cookie.freshness())
+}
+
+/// 找不到可用会话 Cookie 时的错误
+pub(super) fn no_session_error(cookies: &[SessionCookie], source: &str) -> IflyrecError {
+    if cookies.is_empty() {
+        IflyrecError::AuthError(format!("no iflyrec session cookie in {source}"))
+    } else {
+        IflyrecError::AuthError(format!(
+            "all {} iflyrec session cookie(s) in {source} have expired",
+            cookies.len()
+        ))
+    }
+}
+
+/// 会话 Cookie 的匹配条件
+///
+/// 未指定时名称需包含 `session`、域名需包含 `iflyrec`（均不区分大小写）；
+/// 指定后名称需完全一致，域名需一致或为其子域名
+#[derive(Clone, Debug, Default)]
+pub(super) struct CookieFilter {
+    pub(super) name: Option<String>,
+    pub(super) host: Option<String>,
+}
+
+impl CookieFilter {
+    pub(super) fn matches(&self, name: &str, host: &str) -> bool {
+        let name_matches = match &self.name {
+            Some(expected) => name == expected,
+            None => name.to_ascii_lowercase().contains(DEFAULT_NAME_PATTERN),
+        };
+        let host = host.trim_start_matches('.').to_ascii_lowercase();
+        let host_matches = match &self.host {
+            Some(expected) => {
+                let expected = expected.trim_start_matches('.').to_ascii_lowercase();
+                host == expected || host.ends_with(&format!(".{expected}"))
+            }
+            None => host.contains(DEFAULT_HOST_PATTERN),
+        };
+        name_matches && host_matches
+    }
+}
+
+/// 从 Cookie 数据库读取会话ID
+///
+/// 忽略已过期的 Cookie，取最近访问（其次最近创建）的一个。
+/// 数据库被正在运行的浏览器锁定时，会先复制到临时目录再读取。
+#[derive(Clone, Debug)]
+pub struct CookieDbSession {
+    path: PathBuf,
+    key_password: Option<String>,
+    filter: CookieFilter,
+}
+
+impl CookieDbSession {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            key_password: None,
+            filter: CookieFilter::default(),
+        }
+    }
+
+    /// 设置解密 Chromium Cookie 的口令
+    pub fn key_password(mut self, password: impl Into<String>) -> Self {
+        self.key_password = Some(password.into());
+        self
+    }
+
+    /// 只读取指定名称的 Cookie
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.filter.name = Some(name.into());
+        self
+    }
+
+    /// 只读取指定域名（及其子域名）的 Cookie
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.filter.host = Some(host.into());
+        self
+    }
+
+    /// 列出所有匹配的会话 Cookie（包括已过期的），最新的在前
+    pub fn cookies(&self) -> Result<Vec<SessionCookie>, IflyrecError> {
+        let mut cookies = read_cookie_db(&self.path, self.key_password.as_deref(), &self.filter)?;
+        sort_freshest_first(&mut cookies);
+        Ok(cookies)
+    }
+}
+
+impl SessionProvider for CookieDbSession {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        let cookies = self.cookies()?;
+        match freshest(&cookies) {
+            Some(cookie) => Ok(cookie.value.clone()),
+            None => Err(no_session_error(&cookies, &self.path.display().to_string())),
+        }
+    }
+}
+
+/// Cookie 数据库格式
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CookieSchema {
+    Chromium,
+    Firefox,
+}
+
+/// 读取数据库中匹配的会话 Cookie，数据库被锁定时读取其副本
+pub(super) fn read_cookie_db(
+    path: &Path,
+    key_password: Option<&str>,
+    filter: &CookieFilter,
+) -> Result<Vec<SessionCookie>, IflyrecError> {
+    let cookies = match read_cookies(path, key_password, filter) {
+        Err(IflyrecError::DatabaseError(e)) if is_locked(&e) => {
+            tracing::info!("{} 已被锁定，复制后读取", path.display());
+            read_copy(path, key_password, filter)
+        }
+        result => result,
+    }?;
+    Ok(cookies
+        .into_iter()
+        .map(|cookie| SessionCookie {
+            source: path.to_path_buf(),
+            ..cookie
+        })
+        .collect())
+}
+
+fn read_cookies(
+    path: &Path,
+    key_password: Option<&str>,
+    filter: &CookieFilter,
+) -> Result<Vec<SessionCookie>, IflyrecError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    match detect_schema(&conn, path)? {
+        CookieSchema::Chromium => chromium::read_session_cookies(
+            &conn,
+            &ChromiumCookieDecryptor::new(key_password),
+            filter,
+        ),
+        CookieSchema::Firefox => firefox::read_session_cookies(&conn, filter),
+    }
+}
+
+/// 将数据库及其 WAL 文件复制到临时目录后读取
+fn read_copy(
+    path: &Path,
+    key_password: Option<&str>,
+    filter: &CookieFilter,
+) -> Result<Vec<SessionCookie>, IflyrecError> {
+    let dir = temporary_path("");
+    fs::create_dir_all(&dir)?;
+
+    let result = (|| {
+        let copy = dir.join("cookies.sqlite");
+        fs::copy(path, &copy)?;
+        let mut wal = path.as_os_str().to_owned();
+        wal.push("-wal");
+        if Path::new(&wal).exists() {
+            fs::copy(&wal, dir.join("cookies.sqlite-wal"))?;
+        }
+        read_cookies(&copy, key_password, filter)
+    })();
+
+    if let Err(e) = fs::remove_dir_all(&dir) {
+        tracing::warn!("清理临时目录 {} 失败：{e}", dir.display());
+    }
+    result
+}
+
+fn detect_schema(conn: &Connection, path: &Path) -> Result<CookieSchema, IflyrecError> {
+    let tables = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table'")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if tables.iter().any(|table| table == "cookies") {
+        Ok(CookieSchema::Chromium)
+    } else if tables.iter().any(|table| table == "moz_cookies") {
+        Ok(CookieSchema::Firefox)
+    } else {
+        Err(IflyrecError::InvalidParameter(format!(
+            "{} is not a Chromium or Firefox cookie database",
+            path.display()
+        )))
+    }
+}
+
+fn is_locked(error: &rusqlite::Error) -> bool {
+    matches!(
+        error.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    )
+}
+
+/// 将自 Unix 纪元起的微秒数转换为系统时间，非正数视为未设置
+pub(super) fn unix_time_micros(micros: i64) -> Option<SystemTime> {
+    let micros = u64::try_from(micros).ok().filter(|micros| *micros > 0)?;
+    UNIX_EPOCH.checked_add(Duration::from_micros(micros))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn cookie(
+        value: &str,
+        expires: Option<SystemTime>,
+        last_access: Option<SystemTime>,
+        created: Option<SystemTime>,
+    ) -> SessionCookie {
+        SessionCookie {
+            name: "sessionId".to_string(),
+            host: ".iflyrec.com".to_string(),
+            value: value.to_string(),
+            expires,
+            last_access,
+            created,
+            source: PathBuf::from("Cookies"),
+        }
+    }
+
+    fn filter(name: Option<&str>, host: Option<&str>) -> CookieFilter {
+        CookieFilter {
+            name: name.map(str::to_string),
+            host: host.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn expiry_is_checked_against_now() {
+        let now = SystemTime::now();
+        assert!(cookie("a", Some(now - HOUR), None, None).is_expired());
+        assert!(!cookie("a", Some(now + HOUR), None, None).is_expired());
+        assert!(!cookie("a", None, None, None).is_expired());
+    }
+
+    #[test]
+    fn freshest_skips_expired_cookies() {
+        let now = SystemTime::now();
+        let cookies = [
+            cookie("expired", Some(now - HOUR), Some(now), Some(now)),
+            cookie(
+                "valid",
+                Some(now + HOUR),
+                Some(now - HOUR),
+                Some(now - HOUR),
+            ),
+        ];
+        assert_eq!(freshest(&cookies).unwrap().value, "valid");
+
+        let all_expired = [cookie("expired", Some(now - HOUR), Some(now), None)];
+        assert!(freshest(&all_expired).is_none());
+        assert!(matches!(
+            no_session_error(&all_expired, "Cookies"),
+            IflyrecError::AuthError(message) if message.contains("expired")
+        ));
+    }
+
+    #[test]
+    fn last_access_wins_over_created() {
+        let now = SystemTime::now();
+        let cookies = [
+            cookie("newly-created", None, Some(now - 2 * HOUR), Some(now)),
+            cookie("recently-used", None, Some(now), Some(now - 3 * HOUR)),
+        ];
+        assert_eq!(freshest(&cookies).unwrap().value, "recently-used");
+    }
+
+    #[test]
+    fn created_breaks_last_access_ties() {
+        let now = SystemTime::now();
+        let cookies = [
+            cookie("older", None, Some(now), Some(now - HOUR)),
+            cookie("newer", None, Some(now), Some(now)),
+        ];
+        assert_eq!(freshest(&cookies).unwrap().value, "newer");
+    }
+
+    #[test]
+    fn sorts_freshest_first_including_expired() {
+        let now = SystemTime::now();
+        let mut cookies = vec![
+            cookie("unknown", None, None, None),
+            cookie("expired", Some(now - HOUR), Some(now), None),
+            cookie("old", None, Some(now - HOUR), None),
+        ];
+        sort_freshest_first(&mut cookies);
+        let order: Vec<&str> = cookies.iter().map(|c| c.value.as_str()).collect();
+        assert_eq!(order, ["expired", "old", "unknown"]);
+    }
+
+    #[test]
+    fn default_filter_matches_fragments_case_insensitively() {
+        let filter = filter(None, None);
+        assert!(filter.matches("SESSIONID", ".www.IFLYREC.com"));
+        assert!(filter.matches("x-session", "iflyrec.com"));
+        assert!(!filter.matches("token", "iflyrec.com"));
+        assert!(!filter.matches("sessionId", "example.com"));
+    }
+
+    #[test]
+    fn name_filter_requires_exact_match() {
+        let filter = filter(Some("sessionId"), None);
+        assert!(filter.matches("sessionId", "iflyrec.com"));
+        assert!(!filter.matches("sessionid", "iflyrec.com"));
+        assert!(!filter.matches("sessionId2", "iflyrec.com"));
+    }
+
+    #[test]
+    fn host_filter_matches_domain_and_subdomains() {
+        let filter = filter(None, Some(".IflyRec.com"));
+        assert!(filter.matches("sessionId", "iflyrec.com"));
+        assert!(filter.matches("sessionId", ".iflyrec.com"));
+        assert!(filter.matches("sessionId", "www.IFLYREC.COM"));
+        assert!(!filter.matches("sessionId", "notiflyrec.com"));
+        assert!(!filter.matches("sessionId", "iflyrec.com.example.org"));
+    }
+
+    #[test]
+    fn detects_cookie_db_schema() {
+        let path = Path::new("cookies.sqlite");
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE moz_cookies (id INTEGER PRIMARY KEY, name TEXT);")
+            .unwrap();
+        assert_eq!(detect_schema(&conn, path).unwrap(), CookieSchema::Firefox);
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE cookies (host_key TEXT, name TEXT);")
+            .unwrap();
+        assert_eq!(detect_schema(&conn, path).unwrap(), CookieSchema::Chromium);
+
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE other (id INTEGER);")
+            .unwrap();
+        assert!(matches!(
+            detect_schema(&conn, path),
+            Err(IflyrecError::InvalidParameter(_))
+        ));
+    }
+}
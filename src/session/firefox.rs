@@ -0,0 +1,342 @@
+//! Firefox Cookie
+//!
+//! 读取 Firefox 配置文件中的 `cookies.sqlite`（`moz_cookies` 表），并自动查找本机的配置文件
+
+use super::SessionProvider;
+use super::cookie_db::{
+    CookieFilter, SessionCookie, freshest, no_session_error, read_cookie_db, sort_freshest_first,
+    unix_time_micros,
+};
+use crate::error::IflyrecError;
+
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// 大于该值的 `expiry` 以毫秒为单位（新版本 Firefox），否则以秒为单位
+const EXPIRY_MILLIS_THRESHOLD: i64 = 100_000_000_000;
+
+/// 读取 Firefox 格式数据库（`moz_cookies` 表）中与 `filter` 匹配的会话 Cookie
+pub(super) fn read_session_cookies(
+    conn: &Connection,
+    filter: &CookieFilter,
+) -> Result<Vec<SessionCookie>, IflyrecError> {
+    let mut stmt = conn
+        .prepare("SELECT host, name, value, expiry, lastAccessed, creationTime FROM moz_cookies")?;
+    let rows = stmt.query_map([], |row| {
+        Ok(SessionCookie {
+            host: row.get(0)?,
+            name: row.get(1)?,
+            value: row.get(2)?,
+            expires: row.get::<_, Option<i64>>(3)?.and_then(expiry_time),
+            last_access: row.get::<_, Option<i64>>(4)?.and_then(unix_time_micros),
+            created: row.get::<_, Option<i64>>(5)?.and_then(unix_time_micros),
+            source: PathBuf::new(),
+        })
+    })?;
+
+    let mut cookies = Vec::new();
+    for cookie in rows {
+        let cookie = cookie?;
+        if filter.matches(&cookie.name, &cookie.host) && !cookie.value.is_empty() {
+            cookies.push(cookie);
+        }
+    }
+    Ok(cookies)
+}
+
+fn expiry_time(expiry: i64) -> Option<SystemTime> {
+    if expiry > EXPIRY_MILLIS_THRESHOLD {
+        unix_time_micros(expiry.saturating_mul(1_000))
+    } else {
+        unix_time_micros(expiry.saturating_mul(1_000_000))
+    }
+}
+
+/// 本机可能存放 Firefox 配置文件的目录
+fn firefox_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        roots.push(home.join(".mozilla/firefox"));
+        roots.push(home.join("snap/firefox/common/.mozilla/firefox"));
+        roots.push(home.join(".var/app/org.mozilla.firefox/.mozilla/firefox"));
+        roots.push(home.join("Library/Application Support/Firefox"));
+    }
+    if let Some(app_data) = std::env::var_os("APPDATA").map(PathBuf::from) {
+        roots.push(app_data.join("Mozilla/Firefox"));
+    }
+    roots
+}
+
+/// 查找本机的 Firefox 配置文件目录
+///
+/// 按 `profiles.ini` 解析，默认配置文件排在前面；没有 `profiles.ini` 时
+/// 列出含有 `cookies.sqlite` 的子目录
+pub fn firefox_profiles() -> Vec<PathBuf> {
+    firefox_roots()
+        .iter()
+        .filter(|root| root.is_dir())
+        .flat_map(|root| profiles_in(root))
+        .collect()
+}
+
+fn profiles_in(root: &Path) -> Vec<PathBuf> {
+    let Ok(ini) = std::fs::read_to_string(root.join("profiles.ini")) else {
+        return std::fs::read_dir(root)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.join("cookies.sqlite").is_file())
+            .collect();
+    };
+
+    // (路径, 是否默认)
+    let mut profiles: Vec<(PathBuf, bool)> = Vec::new();
+    let mut install_defaults = Vec::new();
+    for section in parse_ini(&ini) {
+        if section.name.starts_with("Install") {
+            if let Some(path) = section.get("Default") {
+                install_defaults.push(root.join(path));
+            }
+        } else if section.name.starts_with("Profile")
+            && let Some(path) = section.get("Path")
+        {
+            let path = if section.get("IsRelative") == Some("0") {
+                PathBuf::from(path)
+            } else {
+                root.join(path)
+            };
+            profiles.push((path, section.get("Default") == Some("1")));
+        }
+    }
+
+    // 安装默认的配置文件优先，其次是标记为默认的配置文件
+    profiles.sort_by_key(|(path, default)| (!install_defaults.contains(path), !*default));
+    profiles.into_iter().map(|(path, _)| path).collect()
+}
+
+struct IniSection<'a> {
+    name: &'a str,
+    entries: Vec<(&'a str, &'a str)>,
+}
+
+impl<'a> IniSection<'a> {
+    fn get(&self, key: &str) -> Option<&'a str> {
+        self.entries
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, value)| *value)
+    }
+}
+
+fn parse_ini(content: &str) -> Vec<IniSection<'_>> {
+    let mut sections: Vec<IniSection> = Vec::new();
+    for line in content.lines().map(str::trim) {
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            sections.push(IniSection {
+                name,
+                entries: Vec::new(),
+            });
+        } else if let (Some(section), Some((key, value))) =
+            (sections.last_mut(), line.split_once('='))
+        {
+            section.entries.push((key.trim(), value.trim()));
+        }
+    }
+    sections
+}
+
+/// 从本机 Firefox 配置文件读取会话ID
+///
+/// 读取各配置文件的 `cookies.sqlite`，取所有未过期的会话 Cookie 中最新的一个
+#[derive(Clone, Debug, Default)]
+pub struct FirefoxSession {
+    profiles: Option<Vec<PathBuf>>,
+    filter: CookieFilter,
+}
+
+impl FirefoxSession {
+    /// 自动查找配置文件
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 只读取指定的配置文件目录
+    pub fn with_profiles(profiles: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        Self {
+            profiles: Some(profiles.into_iter().map(Into::into).collect()),
+            ..Self::default()
+        }
+    }
+
+    /// 只读取指定名称的 Cookie
+    pub fn cookie_name(mut self, name: impl Into<String>) -> Self {
+        self.filter.name = Some(name.into());
+        self
+    }
+
+    /// 只读取指定域名（及其子域名）的 Cookie
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.filter.host = Some(host.into());
+        self
+    }
+
+    /// 列出所有配置文件中匹配的会话 Cookie（包括已过期的），最新的在前
+    ///
+    /// 无法读取的配置文件会被跳过
+    pub fn cookies(&self) -> Vec<SessionCookie> {
+        let profiles = self.profiles.clone().unwrap_or_else(firefox_profiles);
+        let mut cookies = Vec::new();
+        for profile in &profiles {
+            let path = profile.join("cookies.sqlite");
+            if !path.is_file() {
+                continue;
+            }
+            match read_cookie_db(&path, None, &self.filter) {
+                Ok(found) => cookies.extend(found),
+                Err(e) => tracing::warn!("读取 {} 失败：{e}", path.display()),
+            }
+        }
+        sort_freshest_first(&mut cookies);
+        cookies
+    }
+}
+
+impl SessionProvider for FirefoxSession {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        let cookies = self.cookies();
+        match freshest(&cookies) {
+            Some(cookie) => Ok(cookie.value.clone()),
+            None => Err(no_session_error(&cookies, "Firefox profiles")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::temporary_path;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const PROFILES_INI: &str = "\
+[Install4F96D1932A9F858E]
+Default=Profiles/def.default-release
+Locked=1
+
+[Profile1]
+Name=default
+IsRelative=1
+Path=Profiles/abc.default
+Default=1
+
+[Profile0]
+Name=default-release
+IsRelative=1
+Path=Profiles/def.default-release
+
+[Profile2]
+Name=external
+IsRelative=0
+Path=/mnt/data/firefox/ext
+
+[General]
+StartWithLastProfile=1
+Version=2
+";
+
+    #[test]
+    fn parses_ini_sections() {
+        let sections = parse_ini(PROFILES_INI);
+        let names: Vec<&str> = sections.iter().map(|s| s.name).collect();
+        assert_eq!(
+            names,
+            [
+                "Install4F96D1932A9F858E",
+                "Profile1",
+                "Profile0",
+                "Profile2",
+                "General"
+            ]
+        );
+        assert_eq!(sections[1].get("Path"), Some("Profiles/abc.default"));
+        assert_eq!(sections[1].get("Default"), Some("1"));
+        assert_eq!(sections[2].get("Default"), None);
+    }
+
+    #[test]
+    fn orders_install_default_first_and_keeps_absolute_paths() {
+        let root = temporary_path("");
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("profiles.ini"), PROFILES_INI).unwrap();
+
+        let profiles = profiles_in(&root);
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(
+            profiles,
+            [
+                root.join("Profiles/def.default-release"),
+                root.join("Profiles/abc.default"),
+                PathBuf::from("/mnt/data/firefox/ext"),
+            ]
+        );
+    }
+
+    #[test]
+    fn without_profiles_ini_lists_directories_with_cookies() {
+        let root = temporary_path("");
+        std::fs::create_dir_all(root.join("with-cookies")).unwrap();
+        std::fs::create_dir_all(root.join("empty")).unwrap();
+        std::fs::write(root.join("with-cookies/cookies.sqlite"), b"").unwrap();
+
+        let profiles = profiles_in(&root);
+
+        std::fs::remove_dir_all(&root).unwrap();
+        assert_eq!(profiles, [root.join("with-cookies")]);
+    }
+
+    #[test]
+    fn expiry_accepts_seconds_and_milliseconds() {
+        let expected = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(expiry_time(1_700_000_000), Some(expected));
+        assert_eq!(expiry_time(1_700_000_000_000), Some(expected));
+        assert_eq!(expiry_time(0), None);
+    }
+
+    #[test]
+    fn reads_matching_cookies_from_moz_cookies() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE moz_cookies (
+                 id INTEGER PRIMARY KEY, name TEXT, value TEXT, host TEXT,
+                 expiry INTEGER, lastAccessed INTEGER, creationTime INTEGER
+             );
+             INSERT INTO moz_cookies (name, value, host, expiry, lastAccessed, creationTime) VALUES
+                 ('sessionId', 'abc', '.iflyrec.com', 1700000000000, 1600000000000000, 1500000000000000),
+                 ('sessionId', '', '.iflyrec.com', 1700000000, 0, 0),
+                 ('other', 'xyz', '.iflyrec.com', 1700000000, 0, 0);",
+        )
+        .unwrap();
+
+        let cookies = read_session_cookies(&conn, &CookieFilter::default()).unwrap();
+
+        assert_eq!(cookies.len(), 1);
+        let cookie = &cookies[0];
+        assert_eq!(cookie.value, "abc");
+        assert_eq!(
+            cookie.expires,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(
+            cookie.last_access,
+            Some(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        );
+        assert_eq!(
+            cookie.created,
+            Some(UNIX_EPOCH + Duration::from_secs(1_500_000_000))
+        );
+    }
+}
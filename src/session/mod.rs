@@ -0,0 +1,144 @@
+//! 会话ID提取
+//!
+//! 通过 [`SessionProvider`] 从字符串、环境变量、文件、讯飞听见客户端（Electron）
+//! 或 Firefox 的 Cookie 数据库中获取会话ID，多个来源可以用 [`SessionChain`] 组合为回退链
+
+mod chromium;
+mod cookie_db;
+mod firefox;
+
+pub use chromium::{ChromiumCookieDecryptor, DEFAULT_CHROMIUM_KEY_PASSWORD};
+pub use cookie_db::{CookieDbSession, SessionCookie};
+pub use firefox::{FirefoxSession, firefox_profiles};
+
+use crate::error::IflyrecError;
+
+use std::path::PathBuf;
+
+/// 默认读取会话ID的环境变量
+pub const DEFAULT_SESSION_ENV: &str = "IFLYREC_SESSION_ID";
+
+/// 会话ID来源
+pub trait SessionProvider: Send + Sync {
+    /// 获取会话ID，找不到时返回 [`IflyrecError::AuthError`]
+    fn session_id(&self) -> Result<String, IflyrecError>;
+}
+
+impl<P: SessionProvider + ?Sized> SessionProvider for Box<P> {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        (**self).session_id()
+    }
+}
+
+/// 直接给定的会话ID
+#[derive(Clone)]
+pub struct LiteralSession(String);
+
+impl LiteralSession {
+    pub fn new(session_id: impl Into<String>) -> Self {
+        Self(session_id.into())
+    }
+}
+
+impl SessionProvider for LiteralSession {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        non_empty(self.0.clone(), "literal session id is empty")
+    }
+}
+
+/// 从环境变量读取会话ID
+#[derive(Clone, Debug)]
+pub struct EnvSession {
+    var: String,
+}
+
+impl EnvSession {
+    pub fn new(var: impl Into<String>) -> Self {
+        Self { var: var.into() }
+    }
+}
+
+impl Default for EnvSession {
+    fn default() -> Self {
+        Self::new(DEFAULT_SESSION_ENV)
+    }
+}
+
+impl SessionProvider for EnvSession {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        let value = std::env::var(&self.var).unwrap_or_default();
+        non_empty(
+            value,
+            &format!("environment variable {} is not set", self.var),
+        )
+    }
+}
+
+/// 从文本文件读取会话ID，忽略首尾空白
+#[derive(Clone, Debug)]
+pub struct FileSession {
+    path: PathBuf,
+}
+
+impl FileSession {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl SessionProvider for FileSession {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        let content = std::fs::read_to_string(&self.path)?;
+        non_empty(
+            content.trim().to_string(),
+            &format!("session file {} is empty", self.path.display()),
+        )
+    }
+}
+
+/// 会话ID回退链
+///
+/// 按顺序尝试各个来源，返回第一个成功获取的会话ID
+#[derive(Default)]
+pub struct SessionChain {
+    providers: Vec<Box<dyn SessionProvider>>,
+}
+
+impl SessionChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 在链尾追加一个来源
+    pub fn with(mut self, provider: impl SessionProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+impl SessionProvider for SessionChain {
+    fn session_id(&self) -> Result<String, IflyrecError> {
+        let mut errors = Vec::with_capacity(self.providers.len());
+        for provider in &self.providers {
+            match provider.session_id() {
+                Ok(session_id) => return Ok(session_id),
+                Err(e) => {
+                    tracing::debug!("会话ID来源不可用：{e}");
+                    errors.push(e.to_string());
+                }
+            }
+        }
+        Err(IflyrecError::AuthError(format!(
+            "no session id found: [{}]",
+            errors.join("; ")
+        )))
+    }
+}
+
+fn non_empty(value: String, reason: &str) -> Result<String, IflyrecError> {
+    if value.is_empty() {
+        Err(IflyrecError::AuthError(reason.to_string()))
+    } else {
+        Ok(value)
+    }
+}